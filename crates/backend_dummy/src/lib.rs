@@ -9,16 +9,19 @@ pub struct DummyBackend {
     last_print_time: Instant,
     pressed_key: Option<Key>,
     frame_count: u64,
+    pixels: Vec<u8>,
 }
 
 impl DummyBackend {
-    pub fn new(_tato: &Tato) -> Self {
+    pub fn new(tato: &Tato) -> Self {
+        let pixels = vec![0u8; tato.video.width() as usize * tato.video.height() as usize * 4];
         Self {
             print_frame_time: true,
             buffer_iter_time: AvgBuffer::new(),
             last_print_time: Instant::now(),
             pressed_key: None,
             frame_count: 0,
+            pixels,
         }
     }
 }
@@ -47,19 +50,17 @@ impl Backend for DummyBackend {
         &mut self,
         _frame_arena: &'a mut A,
         tato: &'a Tato,
-        tilemaps: &[&'a T],
+        banks: &'a [&'a Bank],
+        tilemaps: &'a [&'a T],
     ) where
         &'a T: Into<TilemapRef<'a>>,
         A: ArenaOps<u32, ()>,
     {
         let time_iter = Instant::now();
 
-        // Iterate through all pixels just like Raylib backend does, but don't store them
-        let mut pixel_count = 0;
-        for _color in tato.iter_pixels(tilemaps) {
-            // Silly task just to be busy and not get optimized out
-            pixel_count += 1;
-        }
+        // Render the frame just like the Raylib backend does, but don't display it
+        tato.render_frame_bytes(banks, tilemaps, &mut self.pixels);
+        let pixel_count = self.pixels.len() / 4;
 
         self.buffer_iter_time.push(time_iter.elapsed().as_secs_f64());
         self.frame_count += 1;
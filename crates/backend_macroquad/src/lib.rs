@@ -313,13 +313,7 @@ impl Backend for MquadBackend {
         );
 
         // Copy pixels from video chip
-        for (i, color) in tato.iter_pixels(banks, tilemaps).enumerate() {
-            let index = i * 4;
-            self.pixels[index] = color.r;
-            self.pixels[index + 1] = color.g;
-            self.pixels[index + 2] = color.b;
-            self.pixels[index + 3] = color.a;
-        }
+        tato.render_frame_bytes(banks, tilemaps, &mut self.pixels);
         self.buffer_iter_time.push(time_iter.elapsed().as_secs_f32());
 
         // Update main render texture and queue draw operation
@@ -456,20 +456,15 @@ impl Backend for WinitBackend {
         &mut self,
         _frame_arena: &'a mut Arena<LEN>,
         tato: &'a Tato,
-        tilemaps: &[&'a T],
+        banks: &'a [&'a Bank],
+        tilemaps: &'a [&'a T],
     ) where
         &'a T: Into<TilemapRef<'a>>,
     {
         let time_profile = Instant::now();
 
         // Copy pixels from video chip
-        for (i, color) in tato.iter_pixels(tilemaps).enumerate() {
-            let index = i * 4;
-            self.pixels[index] = color.r;
-            self.pixels[index + 1] = color.g;
-            self.pixels[index + 2] = color.b;
-            self.pixels[index + 3] = color.a;
-        }
+        tato.render_frame_bytes(banks, tilemaps, &mut self.pixels);
         self.buffer_iter_time.push(time_profile.elapsed().as_secs_f64());
 
         // Update canvas texture
@@ -21,7 +21,14 @@ enum DeferredCommand {
     NewTile { path: String, name: String },
     NewMap { path: String, name: String },
     NewAnimationStrip { path: String, name: String, frames_h: u8, frames_v: u8 },
-    NewAnim { name: String, fps: u8, repeat: bool, strip_name: String, frames: Vec<u8> },
+    NewAnim {
+        name: String,
+        fps: u8,
+        repeat: bool,
+        strip_name: String,
+        frames: Vec<u8>,
+        tile_anim: bool,
+    },
 }
 
 /// Converts images into deduplicated tiles, maps, and animations.
@@ -38,6 +45,9 @@ pub struct BankBuilder<'a> {
     pub write_strips: bool,
     /// If true, writes tilemap data.
     pub write_maps: bool,
+    /// Color depth of the generated tiles. Switches to TileMode::Colors16 automatically
+    /// if any tile uses more than 4 colors.
+    pub tile_mode: TileMode,
     #[doc(hidden)]
     pub use_crate_assets: bool,
     name: String,
@@ -50,10 +60,15 @@ pub struct BankBuilder<'a> {
     palette: &'a mut PaletteBuilder,
     strips: HashMap<String, StripBuilder>,
     anims: Vec<Anim>,
+    tile_anims: Vec<TileAnim>,
+    // Cells of the tile animations created so far, keyed by their frames' pixels and fps
+    tile_anim_cells: HashMap<(Vec<Pixels>, u8), Cell>,
     maps: Vec<MapBuilder>,
     single_tiles: Vec<SingleTileBuilder>,
     next_tile: u8,
     deferred_commands: Vec<DeferredCommand>,
+    // Set when a tile can't fit in 4 colors
+    needs_16_colors: bool,
 }
 
 impl<'a> BankBuilder<'a> {
@@ -68,6 +83,7 @@ impl<'a> BankBuilder<'a> {
             write_tiles: true,
             write_strips: true,
             write_maps: true,
+            tile_mode: TileMode::Colors4,
             name: String::from(name),
             pixels: vec![],
             tiles_to_cells: HashMap::new(),
@@ -76,10 +92,13 @@ impl<'a> BankBuilder<'a> {
             palette,
             strips: HashMap::new(),
             anims: vec![],
+            tile_anims: vec![],
+            tile_anim_cells: HashMap::new(),
             maps: vec![],
             single_tiles: vec![],
             next_tile: 0,
             deferred_commands: vec![],
+            needs_16_colors: false,
         }
     }
 
@@ -125,6 +144,29 @@ impl<'a> BankBuilder<'a> {
             repeat,
             strip_name: strip_name.into(),
             frames: frames.into(),
+            tile_anim: false,
+        });
+    }
+
+    /// Same as "new_anim", but the strip is animated by the tile bank itself instead of
+    /// being drawn as a sprite, and always loops. Every cell of the first frame that changes
+    /// in the following frames gets its own animated tile, so BG maps built from that frame
+    /// (i.e. by copying STRIP_[NAME]) animate without affecting other cells that happen to
+    /// use the same pixels. Generates the TILE_ANIMS_[BANK] table instead of an Anim constant.
+    pub fn new_tile_anim<const LEN: usize>(
+        &mut self,
+        anim_name: &str,
+        strip_name: &str,
+        fps: u8,
+        frames: [u8; LEN],
+    ) {
+        self.deferred_commands.push(DeferredCommand::NewAnim {
+            name: anim_name.into(),
+            fps,
+            repeat: true,
+            strip_name: strip_name.into(),
+            frames: frames.into(),
+            tile_anim: true,
         });
     }
 
@@ -160,109 +202,15 @@ impl<'a> BankBuilder<'a> {
 
         println!("cargo:warning=Regenerating bank: {}", full_path);
         // Execute all deferred commands now
-        struct _DeferredCommands;
-        {
-            let commands = self.deferred_commands.clone();
-            for command in commands {
-                match command {
-                    DeferredCommand::NewEmptyTile { name } => {
-                        let img = PalettizedImg::empty(self.palette);
-                        let cells = self.add_tiles(&img);
-                        assert!(cells.len() == 1 && cells[0].len() == 1);
-                        let single_tile = SingleTileBuilder { name, cell: cells[0][0].clone() };
-                        self.single_tiles.push(single_tile);
-                    },
-                    DeferredCommand::NewTile { path, name } => {
-                        let full_path = std::path::Path::new(&settings.asset_import_path)
-                            .join(path)
-                            .to_str()
-                            .expect("Could not convert path to string")
-                            .to_string();
-                        let img = self.load_valid_image(&full_path, 1, 1);
-                        assert!(
-                            img.width == TILE_SIZE as usize,
-                            "Single tile width must be {}",
-                            TILE_SIZE
-                        );
-                        assert!(
-                            img.cols_per_frame == 1 && img.rows_per_frame == 1,
-                            "Single tile must be 1x1 tile (8x8 pixels)"
-                        );
-                        let cells = self.add_tiles(&img);
-                        assert!(cells.len() == 1 && cells[0].len() == 1);
-                        // let tile_name = crate::strip_path_name(&full_path);
-                        let single_tile = SingleTileBuilder { name, cell: cells[0][0].clone() };
-                        self.single_tiles.push(single_tile);
-                    },
-                    DeferredCommand::NewMap { path, name } => {
-                        let full_path = std::path::Path::new(&settings.asset_import_path)
-                            .join(path)
-                            .to_str()
-                            .expect("Could not convert path to string")
-                            .to_string();
-                        let img = self.load_valid_image(&full_path, 1, 1);
-                        let frames = self.add_tiles(&img);
-                        assert!(frames.len() == 1);
-                        let map = MapBuilder {
-                            name,
-                            columns: u8::try_from(img.cols_per_frame).unwrap(),
-                            rows: u8::try_from(img.rows_per_frame).unwrap(),
-                            cells: frames[0].clone(),
-                        };
-                        self.maps.push(map);
-                    },
-                    DeferredCommand::NewAnimationStrip { path, name, frames_h, frames_v } => {
-                        let full_path = std::path::Path::new(&settings.asset_import_path)
-                            .join(path)
-                            .to_str()
-                            .expect("Could not convert path to string")
-                            .to_string();
-                        let img = self.load_valid_image(&full_path, frames_h, frames_v);
-                        let cells = self.add_tiles(&img);
-                        let frame_count = img.frames_h as usize * img.frames_v as usize;
-                        assert!(frame_count > 0);
-                        let strip = StripBuilder {
-                            name: name.clone(),
-                            frames: (0..frame_count)
-                                .map(|i| MapBuilder {
-                                    name: format!("frame_{:02}", i),
-                                    columns: u8::try_from(img.cols_per_frame).unwrap(),
-                                    rows: u8::try_from(img.rows_per_frame).unwrap(),
-                                    cells: cells[i].clone(),
-                                })
-                                .collect(),
-                        };
-                        self.strips.insert(name, strip);
-                    },
-                    DeferredCommand::NewAnim { name, fps, repeat, strip_name, frames } => {
-                        if self.anims.len() == 255 {
-                            panic!("BankBuilder: animation capacity of 256 reached");
-                        }
-
-                        let Some(strip) = self.strips.get(&strip_name) else {
-                            panic!("BankBuilder: Can't find strip name {}", strip_name)
-                        };
-
-                        // Validate
-                        for frame in &frames {
-                            if *frame as usize >= strip.frames.len() {
-                                panic!(
-                                    "BankBuilder: Invalid Anim frame number '{}' on sequence {:?}",
-                                    *frame, frames
-                                );
-                            }
-                        }
-
-                        self.anims.push(Anim {
-                            name,
-                            fps,
-                            repeat,
-                            frames: frames.into(),
-                            strip_name: strip_name.into(),
-                        })
-                    },
-                }
-            }
+        self.execute_commands(&settings);
+        if self.needs_16_colors && self.tile_mode == TileMode::Colors4 {
+            println!(
+                "cargo:warning=Bank {} has tiles with more than 4 colors, using 16 color tiles",
+                self.name
+            );
+            self.tile_mode = TileMode::Colors16;
+            self.clear();
+            self.execute_commands(&settings);
         }
 
         println!("cargo:warning=Creating output file: {}", full_path);
@@ -348,7 +296,19 @@ impl<'a> BankBuilder<'a> {
 
             code.write_line(&format!("  colors: COLORS_{}, ", bank_name,));
             // code.write_line(&format!("  tiles: TILES_{}, ", bank_name));
-            code.write_line(&format!("  tiles: TileBank::from_tiles(&TILES_{}), ", bank_name));
+            let from_tiles = match self.tile_mode {
+                TileMode::Colors4 => "from_tiles",
+                TileMode::Colors16 => "from_tiles_16",
+            };
+            let with_anims = if self.tile_anims.is_empty() {
+                String::new()
+            } else {
+                format!(".with_anims(&TILE_ANIMS_{})", bank_name)
+            };
+            code.write_line(&format!(
+                "  tiles: TileBank::{}(&TILES_{}){}, ",
+                from_tiles, bank_name, with_anims
+            ));
 
             code.write_line("};");
             code.write_line("");
@@ -377,14 +337,23 @@ impl<'a> BankBuilder<'a> {
 
         if self.write_tiles {
             // Write bank tiles
+            let bits_per_pixel = match self.tile_mode {
+                TileMode::Colors4 => 2,
+                TileMode::Colors16 => 4,
+            };
             code.write_line(&format!(
-                "pub const TILES_{}: [Tile<2>; {}] = [",
+                "pub const TILES_{}: [Tile<{}>; {}] = [",
                 bank_name,
+                bits_per_pixel,
                 self.pixels.len() / TILE_LEN
             ));
             // code.write_line("    &[");
             for tile_pixels in self.pixels.chunks(TILE_LEN) {
-                code.write_line(&format!("        {},", crate::format_tile_compact(tile_pixels)));
+                let tile = match self.tile_mode {
+                    TileMode::Colors4 => crate::format_tile_compact(tile_pixels),
+                    TileMode::Colors16 => crate::format_tile_compact_16(tile_pixels),
+                };
+                code.write_line(&format!("        {},", tile));
             }
             code.write_line("    ];");
             // code.write_line("    ],");
@@ -458,6 +427,20 @@ impl<'a> BankBuilder<'a> {
             }
         }
 
+        // Write tile animations
+        if self.write_tiles && !self.tile_anims.is_empty() {
+            code.write_line(&format!(
+                "pub const TILE_ANIMS_{}: [TileAnim; {}] = [",
+                bank_name,
+                self.tile_anims.len()
+            ));
+            for anim in &self.tile_anims {
+                code.write_line(&format!("    {},", crate::format_tile_anim_compact(anim)));
+            }
+            code.write_line("];");
+            code.write_line("");
+        }
+
         // Write single tiles
         if self.write_tiles {
             for tile in &self.single_tiles {
@@ -481,6 +464,7 @@ impl<'a> BankBuilder<'a> {
         let has_content = !self.maps.is_empty()
             || !self.single_tiles.is_empty()
             || !self.anims.is_empty()
+            || !self.tile_anims.is_empty()
             || !self.strips.is_empty()
             || !self.pixels.is_empty()
             || !self.palette.rgb_colors.is_empty();
@@ -503,6 +487,273 @@ impl<'a> BankBuilder<'a> {
         }
     }
 
+    /// Runs all deferred commands, in the order they were added.
+    fn execute_commands(&mut self, settings: &BuildSettings) {
+        let commands = self.deferred_commands.clone();
+        for command in commands {
+            match command {
+                DeferredCommand::NewEmptyTile { name } => {
+                    let img = PalettizedImg::empty(self.palette);
+                    let cells = self.add_tiles(&img);
+                    assert!(cells.len() == 1 && cells[0].len() == 1);
+                    let single_tile = SingleTileBuilder { name, cell: cells[0][0].clone() };
+                    self.single_tiles.push(single_tile);
+                },
+                DeferredCommand::NewTile { path, name } => {
+                    let full_path = std::path::Path::new(&settings.asset_import_path)
+                        .join(path)
+                        .to_str()
+                        .expect("Could not convert path to string")
+                        .to_string();
+                    let img = self.load_valid_image(&full_path, 1, 1);
+                    assert!(
+                        img.width == TILE_SIZE as usize,
+                        "Single tile width must be {}",
+                        TILE_SIZE
+                    );
+                    assert!(
+                        img.cols_per_frame == 1 && img.rows_per_frame == 1,
+                        "Single tile must be 1x1 tile (8x8 pixels)"
+                    );
+                    let cells = self.add_tiles(&img);
+                    assert!(cells.len() == 1 && cells[0].len() == 1);
+                    // let tile_name = crate::strip_path_name(&full_path);
+                    let single_tile = SingleTileBuilder { name, cell: cells[0][0].clone() };
+                    self.single_tiles.push(single_tile);
+                },
+                DeferredCommand::NewMap { path, name } => {
+                    let full_path = std::path::Path::new(&settings.asset_import_path)
+                        .join(path)
+                        .to_str()
+                        .expect("Could not convert path to string")
+                        .to_string();
+                    let img = self.load_valid_image(&full_path, 1, 1);
+                    let frames = self.add_tiles(&img);
+                    assert!(frames.len() == 1);
+                    let map = MapBuilder {
+                        name,
+                        columns: u8::try_from(img.cols_per_frame).unwrap(),
+                        rows: u8::try_from(img.rows_per_frame).unwrap(),
+                        cells: frames[0].clone(),
+                    };
+                    self.maps.push(map);
+                },
+                DeferredCommand::NewAnimationStrip { path, name, frames_h, frames_v } => {
+                    let full_path = std::path::Path::new(&settings.asset_import_path)
+                        .join(path)
+                        .to_str()
+                        .expect("Could not convert path to string")
+                        .to_string();
+                    let img = self.load_valid_image(&full_path, frames_h, frames_v);
+                    self.add_strip(&img, name);
+                },
+                DeferredCommand::NewAnim { name, fps, repeat, strip_name, frames, tile_anim } => {
+                    if self.anims.len() == 255 {
+                        panic!("BankBuilder: animation capacity of 256 reached");
+                    }
+
+                    let Some(strip) = self.strips.get(&strip_name) else {
+                        panic!("BankBuilder: Can't find strip name {}", strip_name)
+                    };
+
+                    // Validate
+                    for frame in &frames {
+                        if *frame as usize >= strip.frames.len() {
+                            panic!(
+                                "BankBuilder: Invalid Anim frame number '{}' on sequence {:?}",
+                                *frame, frames
+                            );
+                        }
+                    }
+
+                    if tile_anim {
+                        self.add_tile_anims(&name, &strip_name, fps, &frames);
+                        continue;
+                    }
+
+                    self.anims.push(Anim {
+                        name,
+                        fps,
+                        repeat,
+                        frames: frames.into(),
+                        strip_name: strip_name.into(),
+                    })
+                },
+            }
+        }
+    }
+
+    /// Clears all generated data, so that commands can be executed again.
+    fn clear(&mut self) {
+        self.pixels.clear();
+        self.tiles_to_cells.clear();
+        self.canonical_tiles.clear();
+        self.original_source_pixels.clear();
+        self.strips.clear();
+        self.anims.clear();
+        self.tile_anims.clear();
+        self.tile_anim_cells.clear();
+        self.maps.clear();
+        self.single_tiles.clear();
+        self.next_tile = 0;
+        self.needs_16_colors = false;
+    }
+
+    /// Splits an image into the frames of a new strip.
+    fn add_strip(&mut self, img: &PalettizedImg, name: String) {
+        let cells = self.add_tiles(img);
+        let frame_count = img.frames_h as usize * img.frames_v as usize;
+        assert!(frame_count > 0);
+        let strip = StripBuilder {
+            name: name.clone(),
+            frames: (0..frame_count)
+                .map(|i| MapBuilder {
+                    name: format!("frame_{:02}", i),
+                    columns: u8::try_from(img.cols_per_frame).unwrap(),
+                    rows: u8::try_from(img.rows_per_frame).unwrap(),
+                    cells: cells[i].clone(),
+                })
+                .collect(),
+        };
+        self.strips.insert(name, strip);
+    }
+
+    /// Creates one tile animation for each cell position that changes across the strip's
+    /// frames, and points the first frame's cells at the animated tiles.
+    fn add_tile_anims(&mut self, name: &str, strip_name: &str, fps: u8, frames: &[u8]) {
+        assert!(
+            frames.len() <= TILE_ANIM_FRAMES,
+            "BankBuilder: Tile animation '{}' has more than {} frames",
+            name,
+            TILE_ANIM_FRAMES
+        );
+        let Some(&first_frame) = frames.first() else {
+            panic!("BankBuilder: Tile animation '{}' has no frames", name)
+        };
+        // Tiles are placeholders until the bank is processed again in 16 color mode
+        if self.needs_16_colors {
+            return;
+        }
+        let strip = &self.strips[strip_name];
+        let cell_count = strip.frames[first_frame as usize].cells.len();
+        for i in 0..cell_count {
+            // Frames are compared by their pixels, so flipped or palette swapped tiles are fine
+            let strip = &self.strips[strip_name];
+            let sources: Vec<Pixels> = frames
+                .iter()
+                .map(|&frame| self.cell_pixels(&strip.frames[frame as usize].cells[i]))
+                .collect();
+            // Static cells, i.e. transparent areas, don't need an animation
+            if sources.iter().all(|pixels| *pixels == sources[0]) {
+                continue;
+            }
+            let key = (sources, fps);
+            let cell = match self.tile_anim_cells.get(&key) {
+                Some(cell) => *cell,
+                None => {
+                    let Some(cell) = self.add_anim_tiles(&key.0, fps) else {
+                        return;
+                    };
+                    self.tile_anim_cells.insert(key, cell);
+                    cell
+                },
+            };
+            let strip = self.strips.get_mut(strip_name).unwrap();
+            strip.frames[first_frame as usize].cells[i] = cell;
+        }
+        assert!(
+            self.tile_anims.len() <= TILE_ANIM_COUNT,
+            "BankBuilder: Tile animation capacity of {} exceeded",
+            TILE_ANIM_COUNT
+        );
+    }
+
+    /// Stores the frames of a single animated cell. The animated tile is never shared with
+    /// other cells, since every cell using it would animate. The frames are drawn with the
+    /// animated cell's colors and flags, so they're stored untransformed, using one set of
+    /// colors for all frames. Returns None if that needs more than 4 colors.
+    fn add_anim_tiles(&mut self, sources: &[Pixels], fps: u8) -> Option<Cell> {
+        let (colors, stored): (Palette, Vec<Pixels>) = match self.tile_mode {
+            TileMode::Colors4 => {
+                let mut used: Vec<u8> = sources
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .collect::<std::collections::HashSet<_>>()
+                    .into_iter()
+                    .collect();
+                if used.len() > 4 {
+                    self.needs_16_colors = true;
+                    return None;
+                }
+                used.sort();
+                let stored = sources
+                    .iter()
+                    .map(|pixels| {
+                        std::array::from_fn(|i| {
+                            used.iter().position(|color| *color == pixels[i]).unwrap() as u8
+                        })
+                    })
+                    .collect();
+                used.resize(4, 0);
+                (Palette::new(used[0], used[1], used[2], used[3]), stored)
+            },
+            TileMode::Colors16 => (Palette::default(), sources.to_vec()),
+        };
+
+        let tile = self.push_tile(&stored[0]);
+        let ids: Vec<TileID> = stored
+            .iter()
+            .map(|pixels| {
+                if *pixels == stored[0] {
+                    return tile;
+                }
+                // Frame tiles never change, so they can be shared
+                self.find_tile(pixels).unwrap_or_else(|| self.push_tile(pixels))
+            })
+            .collect();
+        self.tile_anims.push(TileAnim::new(tile, fps, &ids));
+        Some(Cell { id: tile, flags: TileFlags::default(), colors })
+    }
+
+    /// Pixels (palette colors) displayed by a cell, with its flags and colors applied.
+    fn cell_pixels(&self, cell: &Cell) -> Pixels {
+        let start = cell.id.0 as usize * TILE_LEN;
+        let tile = &self.pixels[start..start + TILE_LEN];
+        let size = TILE_SIZE as usize;
+        std::array::from_fn(|i| {
+            let (x, y) = cell.flags.transform_coords((i % size) as u8, (i / size) as u8, TILE_SIZE);
+            let pixel = tile[y as usize * size + x as usize];
+            match self.tile_mode {
+                TileMode::Colors4 => cell.colors.get(pixel),
+                TileMode::Colors16 => pixel,
+            }
+        })
+    }
+
+    /// Finds a tile with exactly the same stored pixels.
+    fn find_tile(&self, stored: &Pixels) -> Option<TileID> {
+        let index = self.pixels.chunks(TILE_LEN).position(|tile| tile == stored)?;
+        Some(TileID(index as u8))
+    }
+
+    /// Stores a tile without making it available for deduplication.
+    fn push_tile(&mut self, stored: &Pixels) -> TileID {
+        let capacity = match self.tile_mode {
+            TileMode::Colors4 => TILE_COUNT,
+            TileMode::Colors16 => TILE_COUNT / 2,
+        };
+        assert!(
+            (self.next_tile as usize) < capacity.min(u8::MAX as usize),
+            "BankBuilder: tile capacity of {} reached",
+            capacity
+        );
+        let id = TileID(self.next_tile);
+        self.pixels.extend_from_slice(stored);
+        self.next_tile += 1;
+        id
+    }
+
     #[inline(always)]
     fn extract_tile_pixels(img: &PalettizedImg, abs_col: usize, abs_row: usize) -> Pixels {
         let mut tile_data = [0u8; TILE_LEN];
@@ -532,6 +783,19 @@ impl<'a> BankBuilder<'a> {
                         let abs_row = (frame_v * img.rows_per_frame as usize) + row;
 
                         let source_pixels = Self::extract_tile_pixels(img, abs_col, abs_row);
+
+                        if self.tile_mode == TileMode::Colors16 {
+                            frame_tiles.push(self.add_tile_16(source_pixels));
+                            continue;
+                        }
+
+                        // Bail out early, the whole bank will be processed again in 16 color mode
+                        if Self::color_count(&source_pixels) > 4 {
+                            self.needs_16_colors = true;
+                            frame_tiles.push(Cell::default());
+                            continue;
+                        }
+
                         let (canonical_tile, canonical_indices, palette_mapping) =
                             self.create_canonical_tile(&source_pixels);
 
@@ -574,6 +838,51 @@ impl<'a> BankBuilder<'a> {
         frames
     }
 
+    /// 16 color tiles store the palette colors directly, so only exact
+    /// matches (or their flipped/rotated variants) can be reused.
+    fn add_tile_16(&mut self, source_pixels: Pixels) -> Cell {
+        if let Some(existing) = self.tiles_to_cells.get(&source_pixels) {
+            return *existing;
+        }
+
+        assert!(
+            (self.next_tile as usize) < TILE_COUNT / 2,
+            "BankBuilder: 16 color tile capacity of {} reached",
+            TILE_COUNT / 2
+        );
+        let new_cell = Cell {
+            id: TileID(self.next_tile),
+            flags: TileFlags::default(),
+            colors: Palette::default(),
+        };
+        self.pixels.extend_from_slice(&source_pixels);
+        self.tiles_to_cells.insert(source_pixels, new_cell);
+
+        if self.allow_tile_transforms {
+            for flip_x in [false, true] {
+                for flip_y in [false, true] {
+                    for rotation in [false, true] {
+                        let transformed_pixels =
+                            Self::transform_tile(&source_pixels, flip_x, flip_y, rotation);
+                        let mut cell_with_flags = new_cell;
+                        cell_with_flags.flags.set_flip_x(flip_x);
+                        cell_with_flags.flags.set_flip_y(flip_y);
+                        cell_with_flags.flags.set_rotation(rotation);
+                        self.tiles_to_cells.entry(transformed_pixels).or_insert(cell_with_flags);
+                    }
+                }
+            }
+        }
+
+        self.next_tile += 1;
+        new_cell
+    }
+
+    /// Number of unique colors in a tile.
+    fn color_count(pixels: &Pixels) -> usize {
+        pixels.iter().collect::<std::collections::HashSet<_>>().len()
+    }
+
     /// Searches for an existing tile that matches (directly or via transformation).
     fn find_matching_tile(
         &mut self,
@@ -976,3 +1285,177 @@ impl<'a> BankBuilder<'a> {
         (y as usize * map_width as usize) + x as usize
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_palette() -> PaletteBuilder {
+        crate::init_build(BuildSettings {
+            asset_import_path: "import".into(),
+            asset_export_path: "assets".into(),
+            clear_export_path: false,
+            force_reprocess: false,
+        });
+        PaletteBuilder::new("test")
+    }
+
+    fn tile(pixel: impl Fn(usize, usize) -> u8) -> Pixels {
+        std::array::from_fn(|i| pixel(i % TILE_SIZE as usize, i / TILE_SIZE as usize))
+    }
+
+    /// A tile that looks different under every flip and rotation.
+    fn arrow(a: u8, b: u8) -> Pixels {
+        tile(|x, y| if x > y || (y == 7 && x < 3) { a } else { b })
+    }
+
+    /// One frame per entry, laid out horizontally, each a single row of tiles.
+    fn strip_img(frames: &[&[Pixels]]) -> PalettizedImg {
+        let cols = frames[0].len();
+        let width = frames.len() * cols * TILE_SIZE as usize;
+        let mut pixels = vec![0; width * TILE_SIZE as usize];
+        for (f, frame) in frames.iter().enumerate() {
+            for (c, tile) in frame.iter().enumerate() {
+                for (i, pixel) in tile.iter().enumerate() {
+                    let x = (f * cols + c) * TILE_SIZE as usize + i % TILE_SIZE as usize;
+                    pixels[(i / TILE_SIZE as usize) * width + x] = *pixel;
+                }
+            }
+        }
+        PalettizedImg {
+            frames_h: frames.len() as u8,
+            frames_v: 1,
+            cols_per_frame: cols as u8,
+            rows_per_frame: 1,
+            width,
+            pixels,
+        }
+    }
+
+    /// Builds the bank the generated code would contain.
+    fn to_bank(builder: &BankBuilder) -> Bank {
+        let mut bank = match builder.tile_mode {
+            TileMode::Colors4 => Bank::new(),
+            TileMode::Colors16 => Bank::new_16_colors(),
+        };
+        for chunk in builder.pixels.chunks(TILE_LEN) {
+            let size = TILE_SIZE as usize;
+            match builder.tile_mode {
+                TileMode::Colors4 => {
+                    let mut tile = Tile::<2>::default();
+                    for (i, pixel) in chunk.iter().enumerate() {
+                        tile.set_pixel((i % size) as u8, (i / size) as u8, *pixel);
+                    }
+                    bank.append_tile(&tile).unwrap();
+                },
+                TileMode::Colors16 => {
+                    let mut tile = Tile::<4>::default();
+                    for (i, pixel) in chunk.iter().enumerate() {
+                        tile.set_pixel((i % size) as u8, (i / size) as u8, *pixel);
+                    }
+                    bank.append_tile_16(&tile).unwrap();
+                },
+            }
+        }
+        for anim in &builder.tile_anims {
+            bank.tiles.set_anim(*anim);
+        }
+        bank
+    }
+
+    /// Palette colors displayed by a cell on an animation frame, at 10 fps.
+    fn displayed(bank: &Bank, cell: &Cell, frame: usize) -> Pixels {
+        let id = bank.tiles.resolve_anim(cell.id, frame * 6, 60);
+        tile(|x, y| {
+            let (tx, ty) = cell.flags.transform_coords(x as u8, y as u8, TILE_SIZE);
+            bank.tiles.color_index(id, tx, ty, cell.colors)
+        })
+    }
+
+    /// Checks that every cell of the strip's first frame displays each frame in turn.
+    fn assert_animates(builder: &BankBuilder, frames: &[&[Pixels]]) {
+        let bank = to_bank(builder);
+        let cells = &builder.strips["strip"].frames[0].cells;
+        for (f, frame) in frames.iter().enumerate() {
+            for (cell, expected) in cells.iter().zip(frame.iter()) {
+                assert_eq!(displayed(&bank, cell, f), *expected, "frame {f}, cell {cell:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_tile_anim_owns_its_tile() {
+        let mut palette = new_palette();
+        let mut builder = BankBuilder::new("test", &mut palette);
+        let blank = [0; TILE_LEN];
+        let map = builder.add_tiles(&strip_img(&[&[blank, arrow(1, 2)]])).remove(0);
+
+        // The first cell is blank in the first frame only
+        let frames: [&[Pixels]; 2] = [&[blank, arrow(1, 2)], &[arrow(1, 2), arrow(1, 2)]];
+        builder.add_strip(&strip_img(&frames), "strip".into());
+        builder.add_tile_anims("anim", "strip", 10, &[0, 1]);
+
+        assert_eq!(builder.tile_anims.len(), 1);
+        assert_animates(&builder, &frames);
+        // Blank cells elsewhere (i.e. in maps) don't animate
+        let bank = to_bank(&builder);
+        assert_ne!(builder.tile_anims[0].tile, map[0].id);
+        for frame in 0..2 {
+            assert_eq!(displayed(&bank, &map[0], frame), blank);
+            assert_eq!(displayed(&bank, &map[1], frame), arrow(1, 2));
+        }
+        // Static cells are left alone
+        assert_eq!(builder.strips["strip"].frames[0].cells[1], map[1]);
+    }
+
+    #[test]
+    fn test_tile_anim_transformed_frames() {
+        let mut palette = new_palette();
+        let mut builder = BankBuilder::new("test", &mut palette);
+        let flipped = BankBuilder::transform_tile(&arrow(1, 2), true, false, false);
+        let rotated = BankBuilder::transform_tile(&arrow(1, 2), false, false, true);
+
+        // Two cells share a base tile, and the frames reuse flipped, rotated and palette
+        // swapped copies of it. The last cell animates exactly like the first.
+        let frames: [&[Pixels]; 3] = [
+            &[arrow(1, 2), arrow(1, 2), arrow(1, 2)],
+            &[flipped, arrow(3, 2), flipped],
+            &[rotated, arrow(1, 2), rotated],
+        ];
+        builder.add_strip(&strip_img(&frames), "strip".into());
+        builder.add_tile_anims("anim", "strip", 10, &[0, 1, 2]);
+
+        assert_eq!(builder.tile_anims.len(), 2);
+        assert_animates(&builder, &frames);
+        let cells = &builder.strips["strip"].frames[0].cells;
+        assert_eq!(cells[0], cells[2]);
+        assert_ne!(cells[0].id, cells[1].id);
+    }
+
+    #[test]
+    fn test_tile_anim_16_colors() {
+        let mut palette = new_palette();
+        let mut builder = BankBuilder::new("test", &mut palette);
+        // Each frame fits in 4 colors, but not all of them together
+        let frames: [&[Pixels]; 3] = [&[arrow(1, 2)], &[arrow(3, 4)], &[arrow(5, 2)]];
+        builder.add_strip(&strip_img(&frames), "strip".into());
+        builder.add_tile_anims("anim", "strip", 10, &[0, 1, 2]);
+        assert!(builder.needs_16_colors);
+
+        // The bank is then processed again in 16 color mode
+        builder.tile_mode = TileMode::Colors16;
+        builder.clear();
+        builder.add_strip(&strip_img(&frames), "strip".into());
+        builder.add_tile_anims("anim", "strip", 10, &[0, 1, 2]);
+        assert_animates(&builder, &frames);
+    }
+
+    #[test]
+    fn test_tile_anim_code() {
+        let anim = TileAnim::new(TileID(4), 10, &[TileID(4), TileID(7), TileID(2)]);
+        assert_eq!(
+            crate::format_tile_anim_compact(&anim),
+            "TileAnim::new(TileID(4), 10, &[TileID(4), TileID(7), TileID(2)])"
+        );
+    }
+}
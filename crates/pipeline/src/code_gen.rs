@@ -14,6 +14,13 @@ pub(crate) fn format_cell_compact(cell: &tato_video::Cell) -> String {
     format!("Cell::new({}, {}, {})", cell.id.0, cell.flags.0, cell.colors.0)
 }
 
+/// Formats a TileAnim as a constructor call, i.e. for the TILE_ANIMS_[BANK] table.
+pub(crate) fn format_tile_anim_compact(anim: &tato_video::TileAnim) -> String {
+    let frames: Vec<String> =
+        anim.frames[..anim.len as usize].iter().map(|id| format!("TileID({})", id.0)).collect();
+    format!("TileAnim::new(TileID({}), {}, &[{}])", anim.tile.0, anim.fps, frames.join(", "))
+}
+
 /// Formats a Tile as a compact constructor with packed 2-bit pixels.
 pub(crate) fn format_tile_compact(tile_pixels: &[u8]) -> String {
     assert_eq!(tile_pixels.len(), 64, "Tile must have exactly 64 pixels");
@@ -51,6 +58,37 @@ pub(crate) fn format_tile_compact(tile_pixels: &[u8]) -> String {
     )
 }
 
+/// Formats a 16 color Tile as a compact constructor with packed 4-bit pixels.
+pub(crate) fn format_tile_compact_16(tile_pixels: &[u8]) -> String {
+    assert_eq!(tile_pixels.len(), 64, "Tile must have exactly 64 pixels");
+
+    let mut data = [0u64; 4];
+
+    // With 4 bits per pixel and 8x8 tile:
+    // - Each row (cluster) has 8 pixels = 32 bits = 4 bytes
+    // - Each u64 can hold 2 clusters, so data[0] = rows 0-1, data[1] = rows 2-3, etc.
+    for row in 0..8 {
+        let data_idx = row / 2;
+        let cluster_in_u64 = row % 2;
+
+        for col in 0..8 {
+            let pixel_idx = row * 8 + col;
+            let pixel_val = tile_pixels[pixel_idx] & 0x0F; // Ensure 4-bit pixel (0-15)
+
+            // High nibble is the first pixel of each byte
+            let byte_offset = 7 - (cluster_in_u64 * 4) - (col / 2);
+            let bit_offset = byte_offset * 8 + (1 - (col % 2)) * 4;
+
+            data[data_idx] |= (pixel_val as u64) << bit_offset;
+        }
+    }
+
+    format!(
+        "Tile::<4>::new(0x{:016X}, 0x{:016X}, 0x{:016X}, 0x{:016X})",
+        data[0], data[1], data[2], data[3]
+    )
+}
+
 impl CodeWriter {
     /// Creates a new writer for the given file path.
     pub fn new(path: &str) -> Self {
@@ -0,0 +1,60 @@
+use tato_math::{FloatTrig, Vec2};
+
+/// Fixed point value that represents 1.0 in the affine matrix (8.8 format).
+pub const AFFINE_ONE: i16 = 256;
+
+/// A 2x2 matrix plus translation used to sample the main BG map per pixel, instead of
+/// per tile row (a.k.a. "Mode 7"). For every screen pixel the map is sampled at:
+///
+/// `map = matrix * (screen + scroll - origin) + origin`
+///
+/// The matrix maps screen space into map space, so values above 1.0 shrink the map on
+/// screen. It can be changed by the line IRQ on every scanline, for pseudo-3D effects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine {
+    /// Matrix elements, 8.8 fixed point (AFFINE_ONE is 1.0).
+    pub a: i16,
+    pub b: i16,
+    pub c: i16,
+    pub d: i16,
+    /// Center of rotation and scaling, in map pixels.
+    pub origin: Vec2<i16>,
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Affine {
+    /// Samples the map exactly like the regular BG renderer would.
+    pub const IDENTITY: Self = Self::new(AFFINE_ONE, 0, 0, AFFINE_ONE);
+
+    /// Creates a matrix from 8.8 fixed point elements, with the origin at zero.
+    pub const fn new(a: i16, b: i16, c: i16, d: i16) -> Self {
+        Self { a, b, c, d, origin: Vec2 { x: 0, y: 0 } }
+    }
+
+    /// Rotates the map by "angle" radians and scales it by "zoom" (above 1.0 enlarges it).
+    pub fn rotate_zoom(angle: f32, zoom: f32) -> Self {
+        Self::rotate_scale(angle, zoom, zoom)
+    }
+
+    /// Same as [Affine::rotate_zoom], with independent horizontal and vertical zoom.
+    pub fn rotate_scale(angle: f32, zoom_x: f32, zoom_y: f32) -> Self {
+        assert!(zoom_x != 0.0 && zoom_y != 0.0, crate::err!("Affine zoom can't be zero"));
+        let one = AFFINE_ONE as f32;
+        let (sin, cos) = (FloatTrig::sin(angle), FloatTrig::cos(angle));
+        Self::new(
+            (cos / zoom_x * one) as i16,
+            (sin / zoom_x * one) as i16,
+            (-sin / zoom_y * one) as i16,
+            (cos / zoom_y * one) as i16,
+        )
+    }
+
+    pub const fn with_origin(self, x: i16, y: i16) -> Self {
+        Self { origin: Vec2 { x, y }, ..self }
+    }
+}
@@ -1,5 +1,30 @@
 use crate::*;
 
+/// Describes where the contents of a source bank ended up after being appended
+/// to a different bank. Used to fix the cells of tilemaps and anims that came with it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BankRemap {
+    /// Added to every TileID from the source bank.
+    pub tile_offset: u8,
+    /// Maps source bank colors to destination bank colors.
+    pub colors: PaletteRemap,
+}
+
+impl BankRemap {
+    /// Converts a cell that referenced the source bank into one that references the destination.
+    /// Fails if the cell's tile ID plus the offset doesn't fit in a TileID, which means the
+    /// cell wasn't using a tile from the source bank.
+    pub fn cell(&self, cell: Cell) -> Result<Cell, &'static str> {
+        let id =
+            cell.id.0.checked_add(self.tile_offset).ok_or("Remapped tile ID is out of range")?;
+        Ok(Cell {
+            id: TileID(id),
+            flags: cell.flags,
+            colors: cell.colors.remap(&self.colors),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct Bank {
     pub tiles: TileBank,
@@ -11,6 +36,11 @@ impl Bank {
         Self { tiles: TileBank::new(), colors: ColorBank::new() }
     }
 
+    /// A bank where every tile can use all 16 colors. See [TileMode].
+    pub const fn new_16_colors() -> Self {
+        Self { tiles: TileBank::with_mode(TileMode::Colors16), colors: ColorBank::new() }
+    }
+
     pub fn reset(&mut self) {
         self.tiles.reset();
         self.colors.reset_palettes();
@@ -18,12 +48,44 @@ impl Bank {
 
     /// Appends just the tiles from an array. Colors are not processed.
     pub fn append_tile(&mut self, tile: &Tile<2>) -> Result<TileID, &'static str> {
+        if self.tiles.mode != TileMode::Colors4 {
+            return Err("Can't append 4 color tiles to a 16 color bank");
+        }
         let id = self.tiles.add(tile);
         Ok(id)
     }
 
+    /// Appends a single 16 color tile. Colors are not processed.
+    pub fn append_tile_16(&mut self, tile: &Tile<4>) -> Result<TileID, &'static str> {
+        if self.tiles.mode != TileMode::Colors16 {
+            return Err("Can't append 16 color tiles to a 4 color bank");
+        }
+        if self.tiles.count() >= self.tiles.capacity() {
+            return Err("Not enough space in bank for tiles");
+        }
+        Ok(self.tiles.add_16(tile))
+    }
+
+    /// Appends 16 color tiles from an array. Colors are not processed.
+    pub fn append_tiles_16(&mut self, source: &[Tile<4>]) -> Result<u8, &'static str> {
+        if self.tiles.mode != TileMode::Colors16 {
+            return Err("Can't append 16 color tiles to a 4 color bank");
+        }
+        if self.tiles.count() + source.len() > self.tiles.capacity() {
+            return Err("Not enough space in bank for tiles");
+        }
+        let tile_offset = self.tiles.head as u8;
+        for tile in source.iter() {
+            self.tiles.add_16(tile);
+        }
+        Ok(tile_offset)
+    }
+
     /// Appends just the tiles from an array. Colors are not processed.
     pub fn append_tiles(&mut self, source: &[Tile<2>]) -> Result<u8, &'static str> {
+        if self.tiles.mode != TileMode::Colors4 {
+            return Err("Can't append 4 color tiles to a 16 color bank");
+        }
         let tile_offset = u8::try_from(self.tiles.head) //
             .expect("Bank: Error, tile count is invalid");
 
@@ -44,26 +106,75 @@ impl Bank {
         Ok(tile_offset)
     }
 
-    /// Appends the tiles from a different bank without modifying them.
+    /// Appends the tiles from a different bank. 4 color tiles are copied as is, since their
+    /// colors come from the cell palettes. 16 color tiles index the bank colors directly,
+    /// so their pixels are rewritten using "color_remap", if present.
     pub fn append_tiles_from_bank(
         &mut self,
         source: &Bank,
-        _color_remap: Option<PaletteRemap>,
+        color_remap: Option<PaletteRemap>,
     ) -> Result<u8, &'static str> {
+        if source.tiles.mode != self.tiles.mode {
+            return Err("Can't append tiles from a bank with a different tile mode");
+        }
+        let source_anims = source.tiles.anims();
+        if self.tiles.anims().len() + source_anims.len() > TILE_ANIM_COUNT {
+            return Err("Not enough space in bank for tile animations");
+        }
         let source_tile_count = source.tiles.head as usize;
-        self.append_tiles(&source.tiles.tiles[..source_tile_count])
+        let tile_offset = match source.tiles.mode {
+            TileMode::Colors4 => self.append_tiles(&source.tiles.tiles[..source_tile_count])?,
+            TileMode::Colors16 => {
+                if self.tiles.count() + source_tile_count > self.tiles.capacity() {
+                    return Err("Not enough space in bank for tiles");
+                }
+                let tile_offset = self.tiles.head as u8;
+                for id in 0..source_tile_count {
+                    let mut tile = source.tiles.get_16(TileID(id as u8));
+                    if let Some(remap) = &color_remap {
+                        for y in 0..TILE_SIZE {
+                            for x in 0..TILE_SIZE {
+                                tile.set_pixel(x, y, remap[tile.get_pixel(x, y) as usize]);
+                            }
+                        }
+                    }
+                    self.tiles.add_16(&tile);
+                }
+                tile_offset
+            },
+        };
+        // Animations follow their tiles to the new IDs
+        for anim in source_anims {
+            let mut anim = *anim;
+            anim.tile.0 += tile_offset;
+            for frame in &mut anim.frames {
+                frame.0 += tile_offset;
+            }
+            self.tiles.set_anim(anim);
+        }
+        Ok(tile_offset)
     }
 
     /// Appends another bank's data into this bank, useful for combining multiple const Banks.
-    /// Returns the tile offset where the source bank's tiles start in this bank.
-    pub fn append(&mut self, source: &Bank) -> Result<u8, &'static str> {
+    /// Colors already present are reused, so the returned remap should be applied to any cell
+    /// that references the source bank (see [BankRemap::cell] and [Tilemap::remap_colors]).
+    /// On error the bank is left unchanged.
+    pub fn append(&mut self, source: &Bank) -> Result<BankRemap, &'static str> {
         // Check if we have space for tiles
-        let source_tile_count = source.tiles.head;
-        if (self.tiles.head as usize + source_tile_count as usize) > TILE_COUNT {
+        let source_tile_count = source.tiles.count();
+        if self.tiles.count() + source_tile_count > self.tiles.capacity() {
             return Err("Not enough space in bank for tiles");
         }
         let src_len = source.colors.palette_head as usize;
-        let color_remap = self.colors.append(&source.colors.palette[..src_len])?;
-        self.append_tiles_from_bank(source, Some(color_remap))
+        let color_head = self.colors.palette_head;
+        let result = self.colors.append(&source.colors.palette[..src_len]).and_then(|colors| {
+            let tile_offset = self.append_tiles_from_bank(source, Some(colors))?;
+            Ok(BankRemap { tile_offset, colors })
+        });
+        // Colors are appended first, so they're rolled back if the tiles don't fit
+        if result.is_err() {
+            self.colors.restore_state(color_head);
+        }
+        result
     }
 }
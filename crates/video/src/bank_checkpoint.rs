@@ -0,0 +1,70 @@
+use crate::*;
+
+/// Bank counters saved by [Bank::push_checkpoint].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BankCheckpoint {
+    pub tiles: u16,
+    pub colors: u8,
+}
+
+impl Bank {
+    /// Saves the current tile and color counts. Anything added afterwards
+    /// (i.e. a scene's tileset) is discarded by the matching [Bank::pop_checkpoint], while
+    /// anything added before (i.e. shared fonts and UI) stays.
+    pub fn push_checkpoint(&mut self) -> Result<(), &'static str> {
+        let tiles = &mut self.tiles;
+        let Some(slot) = tiles.checkpoints.get_mut(tiles.checkpoint_count as usize) else {
+            return Err("Checkpoint stack overflow");
+        };
+        *slot = BankCheckpoint { tiles: tiles.head, colors: self.colors.palette_head };
+        tiles.checkpoint_count += 1;
+        Ok(())
+    }
+
+    /// Restores the bank to the most recent checkpoint, removing the tiles, colors and color
+    /// cycles added since then, and every tile animation that uses a removed tile.
+    /// An animation replaced with [TileBank::set_anim] after the checkpoint isn't restored.
+    pub fn pop_checkpoint(&mut self) -> Result<BankCheckpoint, &'static str> {
+        let Some(count) = self.tiles.checkpoint_count.checked_sub(1) else {
+            return Err("No checkpoint to pop");
+        };
+        let checkpoint = self.tiles.checkpoints[count as usize];
+        self.tiles.checkpoint_count = count;
+        self.tiles.restore_state(checkpoint.tiles);
+        self.colors.restore_state(checkpoint.colors);
+
+        // Animations may have been removed (which reorders them) or replaced since the
+        // checkpoint, so instead of truncating, any animation that uses a discarded tile
+        // is dropped and the lookup is rebuilt.
+        let tiles = &mut self.tiles;
+        let discarded = |id: TileID| id.0 as u16 >= checkpoint.tiles;
+        let mut kept = 0;
+        for i in 0..tiles.anim_count as usize {
+            let anim = tiles.anims[i];
+            if discarded(anim.tile)
+                || anim.frames[..anim.len as usize].iter().any(|&f| discarded(f))
+            {
+                continue;
+            }
+            tiles.anims[kept] = anim;
+            kept += 1;
+        }
+        tiles.anim_lookup = [0; TILE_COUNT];
+        for (i, anim) in tiles.anims[..kept].iter().enumerate() {
+            tiles.anim_lookup[anim.tile.0 as usize] = i as u8 + 1;
+        }
+        tiles.anim_count = kept as u8;
+
+        for slot in &mut self.colors.cycles {
+            if slot.is_some_and(|cycle| cycle.end >= checkpoint.colors) {
+                *slot = None;
+            }
+        }
+        Ok(checkpoint)
+    }
+
+    /// Number of checkpoints in the stack.
+    pub fn checkpoint_depth(&self) -> usize {
+        self.tiles.checkpoint_count as usize
+    }
+}
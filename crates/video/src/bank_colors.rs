@@ -27,6 +27,7 @@ pub const DEFAULT_MAPPING: [u8; COLORS_PER_PALETTE as usize] =
 pub struct ColorBank {
     pub palette: [RGBA12; COLORS_PER_PALETTE as usize],
     pub(crate) palette_head: u8,
+    pub(crate) cycles: [Option<ColorCycle>; COLOR_CYCLE_COUNT],
 }
 
 impl ColorBank {
@@ -34,6 +35,7 @@ impl ColorBank {
         Self {
             palette: [RGBA12::TRANSPARENT; COLORS_PER_PALETTE as usize],
             palette_head: 0,
+            cycles: [None; COLOR_CYCLE_COUNT],
         }
     }
 
@@ -48,7 +50,11 @@ impl ColorBank {
             i += 1;
         }
 
-        Self { palette: palette_array, palette_head: palette.len() as u8 }
+        Self {
+            palette: palette_array,
+            palette_head: palette.len() as u8,
+            cycles: [None; COLOR_CYCLE_COUNT],
+        }
     }
 
     pub fn color_count(&self) -> u8 {
@@ -58,15 +64,15 @@ impl ColorBank {
     pub fn reset_palettes(&mut self) {
         self.palette = [RGBA12::TRANSPARENT; COLORS_PER_PALETTE as usize];
         self.palette_head = 0;
+        self.cycles = [None; COLOR_CYCLE_COUNT];
     }
 
-    // /// Restore palette counters to previous state (for checkpoint/restore)
-    // /// Warning: Caller must ensure these are valid previous states!
-    // pub fn restore_state(&mut self, color_count: u8, color_mapping_count: u8) {
-    //     assert!(color_count <= COLORS_PER_PALETTE as u8, "Invalid color count");
-    //     assert!(color_mapping_count <= 16, "Invalid color mapping count");
-    //     self.palette_head = color_count;
-    // }
+    /// Restore palette counter to previous state (for checkpoint/restore)
+    /// Warning: Caller must ensure this is a valid previous state!
+    pub(crate) fn restore_state(&mut self, color_count: u8) {
+        assert!(color_count <= COLORS_PER_PALETTE, "Invalid color count");
+        self.palette_head = color_count;
+    }
 
     pub fn push_color(&mut self, color: RGBA12) -> ColorID {
         assert!(self.palette_head < COLORS_PER_PALETTE as u8, "Palette capacity reached");
@@ -86,9 +92,10 @@ impl ColorBank {
         self.palette[id.0 as usize] = color;
     }
 
-    /// Adds unique colors to the bank, and returns a palette remap, if any
+    /// Adds unique colors to the bank, and returns a palette remap. Source indices
+    /// past the end of "colors" are left unchanged.
     pub fn append(&mut self, colors: &[RGBA12]) -> Result<PaletteRemap, &'static str> {
-        let mut color_remap = [0u8; COLORS_PER_PALETTE as usize];
+        let mut color_remap = DEFAULT_MAPPING;
 
         for src_color_idx in 0..colors.len() {
             let src_color = colors[src_color_idx];
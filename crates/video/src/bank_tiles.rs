@@ -1,14 +1,60 @@
 use crate::*;
 
+/// Determines how many colors each tile in a bank can use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TileMode {
+    /// 2 bits per pixel. Each tile uses the 4 colors picked by its Cell's Palette.
+    #[default]
+    Colors4,
+    /// 4 bits per pixel. Pixels index the bank's 16 colors directly, and the Cell's Palette
+    /// is ignored. Each tile takes twice as much memory, so a bank holds half as many tiles.
+    Colors16,
+}
+
+impl TileMode {
+    /// How many 2bpp tile slots a single tile takes in this mode.
+    pub const fn slots_per_tile(self) -> usize {
+        match self {
+            TileMode::Colors4 => 1,
+            TileMode::Colors16 => 2,
+        }
+    }
+}
+
+/// Tile memory. Always stored as 2bpp tiles; in 16 color mode each 4bpp tile
+/// is split across two consecutive slots (rows 0-3 in the first one, rows 4-7 in the second).
 #[derive(Debug, Clone)]
 pub struct TileBank {
     pub tiles: [Tile<2>; TILE_COUNT],
     pub(crate) head: u16,
+    pub(crate) mode: TileMode,
+    pub(crate) anims: [TileAnim; TILE_ANIM_COUNT],
+    pub(crate) anim_count: u8,
+    // Index + 1 of the animation for each tile ID, zero if not animated
+    pub(crate) anim_lookup: [u8; TILE_COUNT],
+    pub(crate) checkpoints: [BankCheckpoint; CHECKPOINT_COUNT],
+    pub(crate) checkpoint_count: u8,
+    // Incremented every time tiles are added or removed
+    pub(crate) version: u32,
 }
 
 impl TileBank {
     pub const fn new() -> Self {
-        Self { tiles: [Tile::<2>::new(0, 0); TILE_COUNT], head: 0 }
+        Self::with_mode(TileMode::Colors4)
+    }
+
+    pub const fn with_mode(mode: TileMode) -> Self {
+        Self {
+            tiles: [Tile::<2>::new(0, 0); TILE_COUNT],
+            head: 0,
+            mode,
+            anims: [TileAnim::new(TileID(0), 0, &[TileID(0)]); TILE_ANIM_COUNT],
+            anim_count: 0,
+            anim_lookup: [0; TILE_COUNT],
+            checkpoints: [BankCheckpoint { tiles: 0, colors: 0 }; CHECKPOINT_COUNT],
+            checkpoint_count: 0,
+            version: 0,
+        }
     }
 
     pub const fn from_tiles(tiles: &[Tile<2>]) -> Self {
@@ -22,37 +68,268 @@ impl TileBank {
             i += 1;
         }
 
-        Self { tiles: tiles_array, head: tiles.len() as u16 }
+        let mut result = Self::new();
+        result.tiles = tiles_array;
+        result.head = tiles.len() as u16;
+        result
+    }
+
+    /// Creates a 16 color bank from 4bpp tiles.
+    pub const fn from_tiles_16(tiles: &[Tile<4>]) -> Self {
+        assert!(tiles.len() <= TILE_COUNT / 2, "TileBank: Too many 16 color tiles");
+        let mut result = Self::with_mode(TileMode::Colors16);
+        let mut i = 0;
+        while i < tiles.len() {
+            let (a, b) = Self::split_tile(&tiles[i]);
+            result.tiles[i * 2] = a;
+            result.tiles[i * 2 + 1] = b;
+            i += 1;
+        }
+        result.head = tiles.len() as u16;
+        result
+    }
+
+    /// Adds tile animations in a const context, i.e. from generated assets.
+    pub const fn with_anims(mut self, anims: &[TileAnim]) -> Self {
+        assert!(anims.len() <= TILE_ANIM_COUNT, "TileBank: Too many tile animations");
+        let mut i = 0;
+        while i < anims.len() {
+            self.anims[i] = anims[i];
+            self.anim_lookup[anims[i].tile.0 as usize] = i as u8 + 1;
+            i += 1;
+        }
+        self.anim_count = anims.len() as u8;
+        self
     }
 
     pub fn reset(&mut self) {
         // Simply sets internal counters to 0. Existing tiles will remain.
         self.head = 0;
+        self.clear_anims();
+        self.checkpoint_count = 0;
+        self.version = self.version.wrapping_add(1);
+    }
+
+    pub fn mode(&self) -> TileMode {
+        self.mode
+    }
+
+    /// Switches the tile mode. Existing tiles become meaningless, so the bank is reset.
+    pub fn set_mode(&mut self, mode: TileMode) {
+        self.mode = mode;
+        self.reset();
     }
 
+    /// Number of tiles in the bank, regardless of how many slots each one takes.
     pub fn count(&self) -> usize {
         self.head as usize
     }
 
+    /// Changes whenever tiles are added or removed, so that tools (i.e. the Dashboard) know
+    /// when to refresh any cached data. Writing to "tiles" directly doesn't update it.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
     pub fn capacity(&self) -> usize {
-        256
+        TILE_COUNT / self.mode.slots_per_tile()
+    }
+
+    /// Makes every BG cell that uses "anim.tile" display the animation frames instead.
+    /// Replaces any previous animation for the same tile.
+    pub fn set_anim(&mut self, anim: TileAnim) {
+        let lookup = &mut self.anim_lookup[anim.tile.0 as usize];
+        if *lookup > 0 {
+            self.anims[*lookup as usize - 1] = anim;
+            return;
+        }
+        assert!(
+            (self.anim_count as usize) < TILE_ANIM_COUNT,
+            err!("Tile animation capacity exceeded")
+        );
+        self.anims[self.anim_count as usize] = anim;
+        self.anim_count += 1;
+        *lookup = self.anim_count;
+    }
+
+    /// Stops animating a tile. Returns the removed animation, if any.
+    pub fn remove_anim(&mut self, tile: TileID) -> Option<TileAnim> {
+        let index = self.anim_lookup[tile.0 as usize].checked_sub(1)? as usize;
+        let removed = self.anims[index];
+        // Move the last animation into the free slot
+        let last = self.anim_count as usize - 1;
+        self.anims[index] = self.anims[last];
+        self.anim_lookup[self.anims[index].tile.0 as usize] = index as u8 + 1;
+        self.anim_lookup[tile.0 as usize] = 0;
+        self.anim_count -= 1;
+        Some(removed)
+    }
+
+    pub fn clear_anims(&mut self) {
+        self.anim_count = 0;
+        self.anim_lookup = [0; TILE_COUNT];
+    }
+
+    pub fn anims(&self) -> &[TileAnim] {
+        &self.anims[..self.anim_count as usize]
     }
 
-    // /// Restore tile counter to a previous state (for checkpoint/restore)
-    // /// Warning: Caller must ensure this is a valid previous state!
-    // pub fn restore_state(&mut self, count: u8) {
-    //     assert!(count as usize <= TILE_COUNT, "Invalid tile count");
-    //     self.head = count;
-    // }
+    /// Returns the tile that should be displayed in place of "id" on a given frame.
+    #[inline]
+    pub fn resolve_anim(&self, id: TileID, frame_number: usize, frame_rate: u8) -> TileID {
+        match self.anim_lookup[id.0 as usize] {
+            0 => id,
+            index => self.anims[index as usize - 1].frame(frame_number, frame_rate),
+        }
+    }
+
+    /// Restore tile counter to a previous state (for checkpoint/restore)
+    /// Warning: Caller must ensure this is a valid previous state!
+    pub(crate) fn restore_state(&mut self, count: u16) {
+        assert!(count as usize <= self.capacity(), "Invalid tile count");
+        self.head = count;
+        self.version = self.version.wrapping_add(1);
+    }
 
     /// Adds a single tile, returns a TileID
     pub fn add(&mut self, tile: &Tile<2>) -> TileID {
+        assert!(self.mode == TileMode::Colors4, err!("Can't add 4 color tile to 16 color bank"));
         let head = u8::try_from(self.head).expect("Tileset capacity exceeded");
         let result = TileID(head);
         // Copy tile data to bank
         let dest_index = self.head as usize;
         self.tiles[dest_index] = *tile;
         self.head += 1;
+        self.version = self.version.wrapping_add(1);
         result
     }
+
+    /// Adds a single 16 color tile, returns a TileID
+    pub fn add_16(&mut self, tile: &Tile<4>) -> TileID {
+        assert!(self.mode == TileMode::Colors16, err!("Can't add 16 color tile to 4 color bank"));
+        assert!(self.count() < self.capacity(), err!("Tileset capacity exceeded"));
+        let result = TileID(self.head as u8);
+        let (a, b) = Self::split_tile(tile);
+        let dest_index = self.head as usize * 2;
+        self.tiles[dest_index] = a;
+        self.tiles[dest_index + 1] = b;
+        self.head += 1;
+        self.version = self.version.wrapping_add(1);
+        result
+    }
+
+    /// Reassembles a 16 color tile from its two slots.
+    pub fn get_16(&self, id: TileID) -> Tile<4> {
+        debug_assert!(self.mode == TileMode::Colors16, err!("Not a 16 color bank"));
+        let a = &self.tiles[Self::slot_16(id)];
+        let b = &self.tiles[Self::slot_16(id) + 1];
+        let mut result = Tile::<4>::default();
+        for (row, cluster) in result.clusters.iter_mut().enumerate() {
+            let half = if row < 4 { a } else { b };
+            let left = half.clusters[(row % 4) * 2].data;
+            let right = half.clusters[(row % 4) * 2 + 1].data;
+            cluster.data = [left[0], left[1], right[0], right[1]];
+        }
+        result
+    }
+
+    /// First of the two slots used by a 16 color tile. Only half of the u8 TileIDs fit in a
+    /// 16 color bank, so larger IDs wrap around instead of reading past the tile memory.
+    #[inline]
+    fn slot_16(id: TileID) -> usize {
+        (id.0 as usize % (TILE_COUNT / 2)) * 2
+    }
+
+    /// Returns a raw pixel value, which is a palette slot (0 to 3)
+    /// in 4 color mode, or a bank color (0 to 15) in 16 color mode.
+    #[inline]
+    pub fn get_pixel(&self, id: TileID, x: u8, y: u8) -> u8 {
+        match self.mode {
+            TileMode::Colors4 => self.tiles[id.0 as usize].get_pixel(x, y),
+            TileMode::Colors16 => {
+                let half = &self.tiles[Self::slot_16(id) + (y as usize / 4)];
+                let cluster = &half.clusters[(y as usize % 4) * 2 + (x as usize / 4)];
+                let byte = cluster.data[(x as usize % 4) / 2];
+                if x & 1 == 0 { byte >> 4 } else { byte & 0x0F }
+            },
+        }
+    }
+
+    /// Sets a raw pixel value (see [TileBank::get_pixel]). Values are masked to the tile mode's range.
+    #[inline]
+    pub fn set_pixel(&mut self, id: TileID, x: u8, y: u8, value: u8) {
+        match self.mode {
+            TileMode::Colors4 => self.tiles[id.0 as usize].set_pixel(x, y, value),
+            TileMode::Colors16 => {
+                let half = &mut self.tiles[Self::slot_16(id) + (y as usize / 4)];
+                let cluster = &mut half.clusters[(y as usize % 4) * 2 + (x as usize / 4)];
+                let byte = &mut cluster.data[(x as usize % 4) / 2];
+                let value = value & 0x0F;
+                *byte =
+                    if x & 1 == 0 { (*byte & 0x0F) | (value << 4) } else { (*byte & 0xF0) | value };
+            },
+        }
+    }
+
+    /// Returns the bank color index of a single pixel, taking the tile mode into account.
+    #[inline]
+    pub fn color_index(&self, id: TileID, x: u8, y: u8, colors: Palette) -> u8 {
+        let pixel = self.get_pixel(id, x, y);
+        match self.mode {
+            TileMode::Colors4 => colors.get(pixel),
+            TileMode::Colors16 => pixel,
+        }
+    }
+
+    /// Returns the bank color indices of an entire tile row, with the flags
+    /// (flip and rotation) already applied.
+    #[inline]
+    pub fn row_colors(&self, id: TileID, flags: TileFlags, row: u8, colors: Palette) -> [u8; 8] {
+        let mut result = [0; PIXELS_PER_CLUSTER as usize];
+        match self.mode {
+            TileMode::Colors4 => {
+                let tile = &self.tiles[id.0 as usize];
+                let cluster = Cluster::from_tile(&tile.clusters, flags, row, TILE_SIZE);
+                for (i, color) in result.iter_mut().enumerate() {
+                    *color = colors.get(cluster.get_subpixel(i as u8));
+                }
+            },
+            TileMode::Colors16 if flags.is_rotated() => {
+                // Rotated rows come from a source column, so each pixel is in a different row
+                for (x, color) in result.iter_mut().enumerate() {
+                    let (tx, ty) = flags.transform_coords(x as u8, row, TILE_SIZE);
+                    *color = self.get_pixel(id, tx, ty);
+                }
+            },
+            TileMode::Colors16 => {
+                let ty = if flags.is_flipped_y() { TILE_SIZE - 1 - row } else { row } as usize;
+                let half = &self.tiles[Self::slot_16(id) + ty / 4];
+                let left = half.clusters[(ty % 4) * 2].data;
+                let right = half.clusters[(ty % 4) * 2 + 1].data;
+                let bytes = [left[0], left[1], right[0], right[1]];
+                for (x, color) in result.iter_mut().enumerate() {
+                    let tx = if flags.is_flipped_x() { TILE_SIZE as usize - 1 - x } else { x };
+                    let byte = bytes[tx / 2];
+                    *color = if tx % 2 == 0 { byte >> 4 } else { byte & 0x0F };
+                }
+            },
+        }
+        result
+    }
+
+    /// Splits a 4bpp tile into two 2bpp slots. Each 4 byte row takes two 2 byte clusters.
+    const fn split_tile(tile: &Tile<4>) -> (Tile<2>, Tile<2>) {
+        let mut a = Tile::<2>::new(0, 0);
+        let mut b = Tile::<2>::new(0, 0);
+        let mut row = 0;
+        while row < TILE_CLUSTER_COUNT {
+            let data = tile.clusters[row].data;
+            let dest = (row % 4) * 2;
+            let half = if row < 4 { &mut a } else { &mut b };
+            half.clusters[dest].data = [data[0], data[1]];
+            half.clusters[dest + 1].data = [data[2], data[3]];
+            row += 1;
+        }
+        (a, b)
+    }
 }
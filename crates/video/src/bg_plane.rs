@@ -0,0 +1,46 @@
+use tato_math::Vec2;
+
+/// Determines where a BG Plane is composited relative to the main BG map and sprites.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PlanePriority {
+    /// Rendered behind the main BG map (i.e. a distant parallax layer).
+    BehindMain,
+    /// Rendered in front of the main BG map, but behind sprites.
+    #[default]
+    AboveMain,
+    /// Rendered in front of the main BG map and sprites.
+    AboveSprites,
+}
+
+/// An additional BG layer, composited with the main BG map in a single pass.
+/// Each plane has its own tilemap, tile bank, scroll offset and wrap mode.
+/// Transparent pixels let the layers underneath show through.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BgPlane {
+    /// Disabled planes are skipped entirely and cost nothing to render.
+    pub enabled: bool,
+    /// Index of the tilemap used by this plane.
+    pub map_bank: u8,
+    /// Index of the tile bank used by this plane.
+    pub tile_bank: u8,
+    /// Offsets the plane's tilemap. Independent from the VideoChip scroll.
+    pub scroll: Vec2<i16>,
+    /// Repeats the plane's tilemap outside its borders.
+    pub wrap: bool,
+    /// Compositing order relative to the main BG map and sprites.
+    pub priority: PlanePriority,
+}
+
+impl BgPlane {
+    /// Creates an enabled plane with no scroll offset and wrapping disabled.
+    pub const fn new(map_bank: u8, tile_bank: u8, priority: PlanePriority) -> Self {
+        Self {
+            enabled: true,
+            map_bank,
+            tile_bank,
+            scroll: Vec2 { x: 0, y: 0 },
+            wrap: false,
+            priority,
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::*;
+
+/// Seeds the flood fill can keep before falling back to slower passes.
+const FILL_STACK_LEN: usize = 256;
+
+/// A block of tiles reserved in a bank and treated as a pixel canvas, i.e. for mini-maps,
+/// drawing tools or procedural textures. Pixels are arranged left to right, top to bottom,
+/// and hold raw tile values: palette slots (0 to 3) in 4 color banks, or bank colors
+/// (0 to 15) in 16 color banks. Out of bounds pixels are ignored by every drawing function.
+///
+/// The Bitmap only stores where its tiles are, so every operation takes the bank it was
+/// created in. Tiles are released like any other tile, i.e. with [Bank::pop_checkpoint].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bitmap {
+    /// First tile reserved in the bank. The rest follow in row order.
+    pub first_tile: TileID,
+    /// Size in tiles.
+    pub columns: u8,
+    pub rows: u8,
+}
+
+impl Bitmap {
+    /// Reserves "columns * rows" blank tiles in a bank.
+    pub fn new(bank: &mut Bank, columns: u8, rows: u8) -> Result<Self, &'static str> {
+        let len = columns as usize * rows as usize;
+        if len == 0 {
+            return Err("Bitmap dimensions can't be zero");
+        }
+        if bank.tiles.count() + len > bank.tiles.capacity() {
+            return Err("Not enough space in bank for bitmap");
+        }
+        let first_tile = TileID(bank.tiles.count() as u8);
+        for _ in 0..len {
+            match bank.tiles.mode() {
+                TileMode::Colors4 => bank.tiles.add(&Tile::default()),
+                TileMode::Colors16 => bank.tiles.add_16(&Tile::default()),
+            };
+        }
+        Ok(Self { first_tile, columns, rows })
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> i16 {
+        self.columns as i16 * TILE_SIZE as i16
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> i16 {
+        self.rows as i16 * TILE_SIZE as i16
+    }
+
+    /// Writes the cells that display the bitmap into a tilemap, with its top-left corner
+    /// at "col" and "row". Palettes only apply to 4 color banks.
+    pub fn place<const LEN: usize>(
+        &self,
+        map: &mut Tilemap<LEN>,
+        col: i16,
+        row: i16,
+        colors: Palette,
+    ) {
+        for tile_row in 0..self.rows {
+            for tile_col in 0..self.columns {
+                let id = TileID(self.first_tile.0 + tile_row * self.columns + tile_col);
+                let cell = Cell { id, flags: TileFlags::default(), colors };
+                map.set_cell(col + tile_col as i16, row + tile_row as i16, cell);
+            }
+        }
+    }
+
+    /// Tile and coordinates within the tile of a bitmap pixel.
+    #[inline]
+    fn locate(&self, x: i16, y: i16) -> Option<(TileID, u8, u8)> {
+        if x < 0 || y < 0 || x >= self.width() || y >= self.height() {
+            return None;
+        }
+        let size = TILE_SIZE as i16;
+        let index = (y / size) * self.columns as i16 + (x / size);
+        Some((TileID(self.first_tile.0 + index as u8), (x % size) as u8, (y % size) as u8))
+    }
+
+    pub fn get_pixel(&self, bank: &Bank, x: i16, y: i16) -> Option<u8> {
+        let (id, tx, ty) = self.locate(x, y)?;
+        Some(bank.tiles.get_pixel(id, tx, ty))
+    }
+
+    #[inline]
+    pub fn set_pixel(&self, bank: &mut Bank, x: i16, y: i16, value: u8) {
+        if let Some((id, tx, ty)) = self.locate(x, y) {
+            bank.tiles.set_pixel(id, tx, ty, value);
+        }
+    }
+
+    /// Sets every pixel to "value".
+    pub fn clear(&self, bank: &mut Bank, value: u8) {
+        self.fill_rect(bank, 0, 0, self.width(), self.height(), value);
+    }
+
+    /// Draws a line including both end points (Bresenham's algorithm).
+    pub fn line(&self, bank: &mut Bank, x0: i16, y0: i16, x1: i16, y1: i16, value: u8) {
+        let dx = (x1 as i32 - x0 as i32).abs();
+        let dy = -(y1 as i32 - y0 as i32).abs();
+        let step_x = if x0 < x1 { 1 } else { -1 };
+        let step_y = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+        loop {
+            self.set_pixel(bank, x, y, value);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let err2 = err * 2;
+            if err2 >= dy {
+                err += dy;
+                x += step_x;
+            }
+            if err2 <= dx {
+                err += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Draws the outline of a rectangle.
+    pub fn rect(&self, bank: &mut Bank, x: i16, y: i16, w: i16, h: i16, value: u8) {
+        if w <= 0 || h <= 0 {
+            return;
+        }
+        // Edges past the i16 range are outside the bitmap anyway
+        let (right, bottom) = (x.saturating_add(w - 1), y.saturating_add(h - 1));
+        self.line(bank, x, y, right, y, value);
+        self.line(bank, x, bottom, right, bottom, value);
+        self.line(bank, x, y, x, bottom, value);
+        self.line(bank, right, y, right, bottom, value);
+    }
+
+    pub fn fill_rect(&self, bank: &mut Bank, x: i16, y: i16, w: i16, h: i16, value: u8) {
+        let (left, top) = (x.max(0), y.max(0));
+        let right = x.saturating_add(w).min(self.width());
+        let bottom = y.saturating_add(h).min(self.height());
+        for py in top..bottom {
+            for px in left..right {
+                self.set_pixel(bank, px, py, value);
+            }
+        }
+    }
+
+    /// Draws the outline of a circle (midpoint algorithm).
+    pub fn circle(&self, bank: &mut Bank, cx: i16, cy: i16, radius: i16, value: u8) {
+        let (cx, cy) = (cx as i32, cy as i32);
+        self.circle_octants(radius, |dx, dy| {
+            for (px, py) in [(dx, dy), (dy, dx)] {
+                self.set_pixel_i32(bank, cx + px, cy + py, value);
+                self.set_pixel_i32(bank, cx - px, cy + py, value);
+                self.set_pixel_i32(bank, cx + px, cy - py, value);
+                self.set_pixel_i32(bank, cx - px, cy - py, value);
+            }
+        });
+    }
+
+    pub fn fill_circle(&self, bank: &mut Bank, cx: i16, cy: i16, radius: i16, value: u8) {
+        let (cx, cy) = (cx as i32, cy as i32);
+        self.circle_octants(radius, |dx, dy| {
+            for (px, py) in [(dx, dy), (dy, dx)] {
+                self.fill_span(bank, cx - px, cx + px, cy + py, value);
+                self.fill_span(bank, cx - px, cx + px, cy - py, value);
+            }
+        });
+    }
+
+    /// Same as [Bitmap::set_pixel], for coordinates that may be outside the i16 range.
+    #[inline]
+    fn set_pixel_i32(&self, bank: &mut Bank, x: i32, y: i32, value: u8) {
+        if let (Ok(x), Ok(y)) = (i16::try_from(x), i16::try_from(y)) {
+            self.set_pixel(bank, x, y, value);
+        }
+    }
+
+    /// Fills a horizontal line from "left" to "right", both included.
+    fn fill_span(&self, bank: &mut Bank, left: i32, right: i32, y: i32, value: u8) {
+        let Ok(y) = i16::try_from(y) else {
+            return;
+        };
+        let left = left.max(0);
+        let right = right.min(self.width() as i32 - 1);
+        if left <= right {
+            self.fill_rect(bank, left as i16, y, (right - left + 1) as i16, 1, value);
+        }
+    }
+
+    /// Calls "plot" with every point of the first octant of a circle. Uses i32 math,
+    /// since the error term of large circles doesn't fit in an i16.
+    fn circle_octants(&self, radius: i16, mut plot: impl FnMut(i32, i32)) {
+        if radius < 0 {
+            return;
+        }
+        let (mut x, mut y) = (radius as i32, 0);
+        let mut err = 1 - x;
+        while x >= y {
+            plot(x, y);
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Replaces the contiguous area (4-way) of pixels with the same value as the starting
+    /// pixel. Doesn't allocate: if the area is too complex for the internal seed stack,
+    /// the remaining pixels are found with extra passes over the bitmap.
+    pub fn flood_fill(&self, bank: &mut Bank, x: i16, y: i16, value: u8) {
+        let Some(target) = self.get_pixel(bank, x, y) else {
+            return;
+        };
+        let value = match bank.tiles.mode() {
+            TileMode::Colors4 => value & 0b11,
+            TileMode::Colors16 => value & 0x0F,
+        };
+        if target == value {
+            return;
+        }
+        // One bit per pixel, marks the pixels filled by this call. Fits the largest
+        // possible bitmap, since each tile has 64 pixels.
+        let mut filled = [0u64; TILE_COUNT];
+        let mut stack = [(0i16, 0i16); FILL_STACK_LEN];
+        stack[0] = (x, y);
+        let mut len = 1;
+
+        // Set when seeds were dropped because the stack was full
+        let mut incomplete = false;
+        loop {
+            while len > 0 {
+                len -= 1;
+                let (x, y) = stack[len];
+                if self.get_pixel(bank, x, y) != Some(target) {
+                    continue;
+                }
+                // Fill the whole span on this row
+                let mut left = x;
+                while self.get_pixel(bank, left - 1, y) == Some(target) {
+                    left -= 1;
+                }
+                let mut right = x;
+                while self.get_pixel(bank, right + 1, y) == Some(target) {
+                    right += 1;
+                }
+                for px in left..=right {
+                    self.set_pixel(bank, px, y, value);
+                    let index = (y as usize * self.width() as usize) + px as usize;
+                    filled[index / 64] |= 1 << (index % 64);
+                }
+                // One seed per span in the rows above and below
+                for ny in [y - 1, y + 1] {
+                    let mut in_span = false;
+                    for px in left..=right {
+                        let is_target = self.get_pixel(bank, px, ny) == Some(target);
+                        if is_target && !in_span {
+                            if len < FILL_STACK_LEN {
+                                stack[len] = (px, ny);
+                                len += 1;
+                            } else {
+                                incomplete = true;
+                            }
+                        }
+                        in_span = is_target;
+                    }
+                }
+            }
+            if !incomplete {
+                break;
+            }
+            // Look for target pixels touching the filled area, to replace the dropped seeds
+            incomplete = false;
+            let is_filled = |x: i16, y: i16| {
+                if x < 0 || y < 0 || x >= self.width() || y >= self.height() {
+                    return false;
+                }
+                let index = (y as usize * self.width() as usize) + x as usize;
+                filled[index / 64] & (1 << (index % 64)) != 0
+            };
+            'scan: for py in 0..self.height() {
+                for px in 0..self.width() {
+                    let touches = is_filled(px - 1, py)
+                        || is_filled(px + 1, py)
+                        || is_filled(px, py - 1)
+                        || is_filled(px, py + 1);
+                    if touches && self.get_pixel(bank, px, py) == Some(target) {
+                        if len == FILL_STACK_LEN {
+                            incomplete = true;
+                            break 'scan;
+                        }
+                        stack[len] = (px, py);
+                        len += 1;
+                    }
+                }
+            }
+            if len == 0 {
+                break;
+            }
+        }
+    }
+
+    /// Copies a tile from the same bank into the bitmap, with its top-left corner at "x" and
+    /// "y". Flip and rotation flags are respected. Pixels with value zero are skipped.
+    pub fn blit(&self, bank: &mut Bank, source: TileID, x: i16, y: i16, flags: TileFlags) {
+        for ty in 0..TILE_SIZE {
+            for tx in 0..TILE_SIZE {
+                let (sx, sy) = flags.transform_coords(tx, ty, TILE_SIZE);
+                let pixel = bank.tiles.get_pixel(source, sx, sy);
+                if pixel != 0 {
+                    self.set_pixel(bank, x + tx as i16, y + ty as i16, pixel);
+                }
+            }
+        }
+    }
+}
@@ -37,6 +37,10 @@ impl Cell {
         Self { flags: self.flags.with_fg(state), ..self }
     }
 
+    pub const fn with_behind_bg_flag(self, state: bool) -> Self {
+        Self { flags: self.flags.with_behind_bg(state), ..self }
+    }
+
     pub const fn with_collision_flag(self, state: bool) -> Self {
         Self { flags: self.flags.with_collision(state), ..self }
     }
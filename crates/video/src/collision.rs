@@ -0,0 +1,75 @@
+use crate::*;
+
+const WORD_COUNT: usize = MAX_SPRITES / 64;
+
+/// "Hardware" collision registers, filled while a frame is rendered. Each sprite
+/// has one bit that records overlaps with other sprites, and one bit that records
+/// overlaps with opaque BG pixels. Only opaque, visible pixels are considered.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpriteCollisions {
+    pub(crate) sprites: [u64; WORD_COUNT],
+    pub(crate) bg: [u64; WORD_COUNT],
+}
+
+/// A single collision event, passed to the collision IRQ.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteCollision {
+    /// Index of the sprite, in the order it was drawn during the frame.
+    pub sprite: u8,
+    /// The other sprite involved, or None if the sprite collided with the BG.
+    pub other: Option<u8>,
+    /// Screen coordinates of the first pixel where the collision was detected.
+    pub x: u16,
+    pub y: u16,
+}
+
+impl SpriteCollisions {
+    /// True if the sprite overlapped another sprite.
+    pub fn with_sprite(&self, sprite: u8) -> bool {
+        Self::get(&self.sprites, sprite)
+    }
+
+    /// True if the sprite overlapped an opaque BG pixel.
+    pub fn with_bg(&self, sprite: u8) -> bool {
+        Self::get(&self.bg, sprite)
+    }
+
+    /// True if any sprite collided with anything.
+    pub fn any(&self) -> bool {
+        self.sprites.iter().chain(self.bg.iter()).any(|&word| word != 0)
+    }
+
+    /// Raw bit set of sprite to sprite collisions, one bit per sprite.
+    pub fn sprite_bits(&self) -> &[u64; WORD_COUNT] {
+        &self.sprites
+    }
+
+    /// Raw bit set of sprite to BG collisions, one bit per sprite.
+    pub fn bg_bits(&self) -> &[u64; WORD_COUNT] {
+        &self.bg
+    }
+
+    /// Sets the sprite bit, returns false if it was already set.
+    pub(crate) fn set_sprite(&mut self, sprite: u8) -> bool {
+        Self::set(&mut self.sprites, sprite)
+    }
+
+    /// Sets the BG bit, returns false if it was already set.
+    pub(crate) fn set_bg(&mut self, sprite: u8) -> bool {
+        Self::set(&mut self.bg, sprite)
+    }
+
+    #[inline]
+    fn get(bits: &[u64; WORD_COUNT], sprite: u8) -> bool {
+        bits[sprite as usize / 64] & (1 << (sprite % 64)) != 0
+    }
+
+    #[inline]
+    fn set(bits: &mut [u64; WORD_COUNT], sprite: u8) -> bool {
+        let word = &mut bits[sprite as usize / 64];
+        let mask = 1 << (sprite % 64);
+        let is_new = *word & mask == 0;
+        *word |= mask;
+        is_new
+    }
+}
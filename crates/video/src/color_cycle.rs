@@ -0,0 +1,179 @@
+use crate::*;
+
+/// Handle to a color cycle registered in a [ColorBank].
+#[derive(Debug, Clone, Copy, Eq, PartialOrd, Ord, PartialEq, Hash, Default)]
+pub struct CycleID(pub u8);
+
+/// How a color cycle behaves once every color in its range was visited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CycleMode {
+    /// Wraps around forever, i.e. waterfalls and conveyor belts.
+    #[default]
+    Loop,
+    /// Reverses direction at each end of the range, i.e. glowing lava or UI highlights.
+    PingPong,
+    /// Stops after the last step, leaving the colors rotated.
+    OneShot,
+}
+
+/// Rotates a range of bank colors over time (a.k.a. palette animation). Every tile
+/// using any of those colors animates, without touching tiles or tilemaps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorCycle {
+    /// First bank color in the range.
+    pub start: u8,
+    /// Last bank color in the range (inclusive).
+    pub end: u8,
+    /// Steps per second.
+    pub fps: u8,
+    pub mode: CycleMode,
+    /// If true colors move towards the start of the range, instead of the end.
+    pub reverse: bool,
+    pub paused: bool,
+    // Video frames counted while not paused
+    pub(crate) elapsed: usize,
+    // Current rotation, from 0 to the range length - 1
+    pub(crate) offset: u8,
+    pub(crate) last_frame: Option<usize>,
+}
+
+impl ColorCycle {
+    pub const fn new(start: u8, end: u8, fps: u8) -> Self {
+        assert!(start < end, "ColorCycle: start must be lower than end");
+        assert!(end < COLORS_PER_PALETTE, "ColorCycle: end is not a valid bank color");
+        Self {
+            start,
+            end,
+            fps,
+            mode: CycleMode::Loop,
+            reverse: false,
+            paused: false,
+            elapsed: 0,
+            offset: 0,
+            last_frame: None,
+        }
+    }
+
+    pub const fn with_mode(self, mode: CycleMode) -> Self {
+        Self { mode, ..self }
+    }
+
+    pub const fn reversed(self) -> Self {
+        Self { reverse: !self.reverse, ..self }
+    }
+
+    /// Number of colors in the range.
+    pub fn color_count(&self) -> u8 {
+        self.end - self.start + 1
+    }
+
+    /// True when a one-shot cycle reached its last step.
+    pub fn is_finished(&self) -> bool {
+        self.mode == CycleMode::OneShot && self.offset == self.color_count() - 1
+    }
+
+    /// Rotation the range should have after "steps" steps.
+    fn offset_at(&self, steps: usize) -> u8 {
+        let len = self.color_count() as usize;
+        let offset = match self.mode {
+            CycleMode::Loop => steps % len,
+            CycleMode::OneShot => steps.min(len - 1),
+            CycleMode::PingPong => {
+                let period = (len - 1) * 2;
+                let pos = steps % period;
+                if pos < len { pos } else { period - pos }
+            },
+        };
+        offset as u8
+    }
+
+    /// Advances the timer, and returns how many colors the range needs to be rotated by.
+    fn advance(&mut self, frame_number: usize, frame_rate: u8) -> isize {
+        let last_frame = self.last_frame.replace(frame_number).unwrap_or(frame_number);
+        if self.paused {
+            return 0;
+        }
+        self.elapsed += frame_number.saturating_sub(last_frame);
+        let frame_duration = (frame_rate as usize / self.fps.max(1) as usize).max(1);
+        let offset = self.offset_at(self.elapsed / frame_duration);
+        let delta = offset as isize - self.offset as isize;
+        self.offset = offset;
+        delta
+    }
+}
+
+impl ColorBank {
+    /// Registers a new color cycle. Banks are owned by the game, not the VideoChip, so cycles
+    /// only advance when [ColorBank::update_cycles] is called: do it once per frame for every
+    /// bank with cycles, i.e. right after "frame_start". Ranges can't overlap, since each
+    /// cycle needs to restore its own colors when removed.
+    pub fn add_cycle(&mut self, cycle: ColorCycle) -> Result<CycleID, &'static str> {
+        let overlaps = self
+            .cycles
+            .iter()
+            .flatten()
+            .any(|existing| existing.start <= cycle.end && cycle.start <= existing.end);
+        if overlaps {
+            return Err("Color cycle overlaps an existing cycle");
+        }
+        let Some(index) = self.cycles.iter().position(|slot| slot.is_none()) else {
+            return Err("Color cycle capacity exceeded");
+        };
+        self.cycles[index] = Some(cycle);
+        Ok(CycleID(index as u8))
+    }
+
+    /// Removes a cycle and restores its colors to their original order.
+    pub fn remove_cycle(&mut self, id: CycleID) -> Option<ColorCycle> {
+        let cycle = self.cycles.get_mut(id.0 as usize)?.take()?;
+        self.rotate_range(&cycle, -(cycle.offset as isize));
+        Some(cycle)
+    }
+
+    pub fn cycle(&self, id: CycleID) -> Option<&ColorCycle> {
+        self.cycles.get(id.0 as usize)?.as_ref()
+    }
+
+    pub fn pause_cycle(&mut self, id: CycleID) {
+        if let Some(Some(cycle)) = self.cycles.get_mut(id.0 as usize) {
+            cycle.paused = true;
+        }
+    }
+
+    pub fn resume_cycle(&mut self, id: CycleID) {
+        if let Some(Some(cycle)) = self.cycles.get_mut(id.0 as usize) {
+            cycle.paused = false;
+        }
+    }
+
+    /// Removes every cycle, restoring their colors.
+    pub fn clear_cycles(&mut self) {
+        for index in 0..COLOR_CYCLE_COUNT {
+            self.remove_cycle(CycleID(index as u8));
+        }
+    }
+
+    /// Advances every cycle according to the VideoChip's frame counter. Should be called
+    /// once per frame, so that cycles also stop while the frame counter is paused.
+    pub fn update_cycles(&mut self, video: &VideoChip) {
+        for index in 0..COLOR_CYCLE_COUNT {
+            let Some(mut cycle) = self.cycles[index] else {
+                continue;
+            };
+            let delta = cycle.advance(video.frame_number, video.frame_rate);
+            self.rotate_range(&cycle, delta);
+            self.cycles[index] = Some(cycle);
+        }
+    }
+
+    /// Moves the colors in the cycle's range "delta" positions, in the cycle's direction.
+    fn rotate_range(&mut self, cycle: &ColorCycle, delta: isize) {
+        if delta == 0 {
+            return;
+        }
+        let range = &mut self.palette[cycle.start as usize..=cycle.end as usize];
+        let len = range.len() as isize;
+        let delta = if cycle.reverse { -delta } else { delta };
+        range.rotate_right(delta.rem_euclid(len) as usize);
+    }
+}
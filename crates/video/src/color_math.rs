@@ -0,0 +1,82 @@
+use crate::*;
+
+/// How a layer's pixels are combined with the pixels underneath them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// Opaque pixels simply replace the pixels underneath.
+    #[default]
+    Normal,
+    /// Adds both colors, clamped to white. Good for lights and glows.
+    Add,
+    /// Subtracts the layer's color from the pixels underneath, clamped to black.
+    Subtract,
+    /// Half way between both colors, i.e. 50% transparency.
+    Average,
+}
+
+impl BlendMode {
+    #[inline]
+    pub fn blend(self, top: RGBA32, bottom: RGBA32) -> RGBA32 {
+        let op = |top: u8, bottom: u8| -> u8 {
+            match self {
+                BlendMode::Normal => top,
+                BlendMode::Add => bottom.saturating_add(top),
+                BlendMode::Subtract => bottom.saturating_sub(top),
+                BlendMode::Average => ((top as u16 + bottom as u16) / 2) as u8,
+            }
+        };
+        RGBA32 {
+            r: op(top.r, bottom.r),
+            g: op(top.g, bottom.g),
+            b: op(top.b, bottom.b),
+            a: bottom.a.max(top.a),
+        }
+    }
+}
+
+/// Color "registers" applied after sprites and BG are composited. Since they work on the
+/// final 8 bit color, fades are much smoother than rewriting the 3 bit palette colors.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ColorMath {
+    /// Master brightness, from -255 (black) to 255 (white). Zero leaves colors untouched.
+    pub brightness: i16,
+    /// Blends sprite pixels with whatever is underneath them (BG tiles or BG color).
+    pub sprite_blend: BlendMode,
+    /// Blends BG tile pixels with the BG color.
+    pub bg_blend: BlendMode,
+    /// Sprite pixels that use this bank color are not drawn, and instead darken the
+    /// pixels underneath them to half brightness.
+    pub shadow_color: Option<u8>,
+}
+
+impl ColorMath {
+    /// False if every register is at its default value, allowing a faster render path.
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.brightness != 0
+            || self.sprite_blend != BlendMode::Normal
+            || self.bg_blend != BlendMode::Normal
+            || self.shadow_color.is_some()
+    }
+
+    /// Applies the master brightness to a single color.
+    #[inline]
+    pub fn apply_brightness(&self, color: RGBA32) -> RGBA32 {
+        let brightness = self.brightness.clamp(-255, 255) as i32;
+        let op = |channel: u8| -> u8 {
+            let channel = channel as i32;
+            if brightness < 0 {
+                (channel * (255 + brightness) / 255) as u8
+            } else {
+                (channel + ((255 - channel) * brightness / 255)) as u8
+            }
+        };
+        RGBA32 { r: op(color.r), g: op(color.g), b: op(color.b), a: color.a }
+    }
+
+    /// Halves the brightness of a color, used by shadow sprites.
+    #[inline]
+    pub fn shadow(color: RGBA32) -> RGBA32 {
+        RGBA32 { r: color.r / 2, g: color.g / 2, b: color.b / 2, a: color.a }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::*;
+
+/// Determines where the horizontal IRQ is called within each line. The IRQ runs before
+/// the pixels at the trigger position are rendered, and only pays for the positions it
+/// actually uses. Positions at or before the viewport's left edge never trigger it, since
+/// the line IRQ already runs before the line starts.
+///
+/// Color math is applied after the whole line is rendered, so changing it mid-line
+/// affects the entire line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum IrqTrigger {
+    /// The horizontal IRQ is never called.
+    #[default]
+    Off,
+    /// A list of screen X positions. Only the first "len" positions are used.
+    Positions { x: [u16; IRQ_X_COUNT], len: u8 },
+    /// Every "n" pixels, counting from the left edge of the screen.
+    Every(u16),
+    /// On every tile column boundary of the main BG map, following its horizontal scroll.
+    TileColumns,
+}
+
+impl IrqTrigger {
+    /// Triggers at up to IRQ_X_COUNT screen X positions. Extra positions are ignored.
+    pub fn at(positions: &[u16]) -> Self {
+        let mut x = [0; IRQ_X_COUNT];
+        let len = positions.len().min(IRQ_X_COUNT);
+        x[..len].copy_from_slice(&positions[..len]);
+        Self::Positions { x, len: len as u8 }
+    }
+
+    /// First trigger position after "x", if any.
+    #[inline]
+    pub fn next(&self, x: u16, scroll_x: i16) -> Option<u16> {
+        match *self {
+            IrqTrigger::Off => None,
+            IrqTrigger::Positions { x: positions, len } => {
+                positions[..len as usize].iter().copied().filter(|&pos| pos > x).min()
+            },
+            IrqTrigger::Every(0) => None,
+            IrqTrigger::Every(n) => x.checked_add(n - (x % n)),
+            IrqTrigger::TileColumns => {
+                let size = TILE_SIZE as u16;
+                let offset = scroll_x.rem_euclid(TILE_SIZE as i16) as u16;
+                x.checked_add(size - ((x + offset) % size))
+            },
+        }
+    }
+}
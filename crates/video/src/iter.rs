@@ -1,12 +1,14 @@
 use core::array::from_fn;
 
 use crate::*;
+use tato_math::Vec2;
 
 // Z-buffer priority constants for compositing
 const Z_BG: u8 = 0; // Background color (lowest priority)
-const Z_BG_TILE: u8 = 1; // Normal background tiles
-const Z_SPRITE: u8 = 2; // Sprites
-const Z_BG_FOREGROUND: u8 = 3; // Background tiles with is_fg() flag (highest priority)
+const Z_SPRITE_BEHIND: u8 = 1; // Sprites with is_behind_bg() flag
+const Z_BG_TILE: u8 = 2; // Normal background tiles
+const Z_SPRITE: u8 = 3; // Sprites
+const Z_BG_FOREGROUND: u8 = 4; // Background tiles with is_fg() flag (highest priority)
 
 /// Renders every pixel as it iterates the entire screen.
 /// All public fields can be manipulated per line with VideoIRQ!
@@ -15,26 +17,43 @@ pub struct PixelIter<'a> {
     vid: &'a VideoChip,
     x: u16,
     y: u16,
-    // irq_x: Option<VideoIRQ>, // TODO: Temporarily disabled for performance reasons
+    irq_x: Option<VideoIRQ>,
     irq_y: Option<VideoIRQ>,
 
     // Pre-rendering state
     wrap_bg: bool,
+    has_windows: bool,
     slot_width: f32, // screen width divided into 16 slots
 
     // Stuff that can be manipulated via Horizontal IRQ
     pub fg_tile_bank: u8,
+    pub fg_color_bank: Option<u8>,
     pub bg_tile_bank: u8,
     pub bg_map_bank: u8,
     pub tile_banks: [&'a Bank; BANK_COUNT],
     pub tilemaps: [TilemapRef<'a>; BG_BANK_COUNT],
+    pub bg_planes: [BgPlane; BG_PLANE_COUNT],
     pub scroll_x: i16,
     pub scroll_y: i16,
+    pub affine: Option<Affine>,
+    pub color_math: ColorMath,
+    pub mosaic: Mosaic,
+    /// Where the horizontal IRQ is called on the current line.
+    pub irq_x_trigger: IrqTrigger,
+    /// Window bounds for the current line, loaded from each Window before the line IRQ.
+    pub window_spans: [WindowSpan; WINDOW_COUNT],
     pub bg_color: RGBA12,   // Background color
     pub crop_color: RGBA12, // Crop color (outside viewport)
 
+    // Collision detection
+    detect_collisions: bool,
+    collisions: SpriteCollisions,
+    sprite_owner: [u8; MAX_RESOLUTION_X], // Which sprite wrote each sprite_buffer pixel
+
     // Dual buffers for parallel processing
     sprite_buffer: [RGBA12; MAX_RESOLUTION_X], // Sprite layer
+    shadow_buffer: [bool; MAX_RESOLUTION_X],   // Sprite pixels that darken the BG instead
+    math_hidden: [bool; MAX_RESOLUTION_X],     // Pixels where windows disable color math
     bg_buffer: [RGBA12; MAX_RESOLUTION_X],     // Background layer (tiles + bg_color)
 }
 
@@ -64,25 +83,42 @@ impl<'a> PixelIter<'a> {
                 if i < bg_maps.len() { bg_maps[i].into() } else { bg_maps[0].into() }
             }),
             fg_tile_bank: vid.fg_tile_bank,
+            fg_color_bank: vid.fg_color_bank,
             bg_tile_bank: vid.bg_tile_bank,
             bg_map_bank: 0,
+            bg_planes: vid.bg_planes,
             x: 0,
             y: 0,
-            // irq_x: vid.irq_x_callback,
+            irq_x: vid.irq_x,
             irq_y: vid.irq_line,
+            irq_x_trigger: vid.irq_x_trigger,
 
             wrap_bg: vid.wrap_bg,
             slot_width: vid.width() as f32 / SLOTS_PER_LINE as f32,
 
             scroll_x: vid.scroll.x,
             scroll_y: vid.scroll.y,
+            affine: vid.affine,
+            color_math: vid.color_math,
+            mosaic: vid.mosaic,
+            window_spans: [WindowSpan::default(); WINDOW_COUNT],
+            has_windows: vid.windows.iter().any(|window| window.enabled),
+            math_hidden: [false; MAX_RESOLUTION_X],
             bg_color: vid.bg_color,
             crop_color: vid.crop_color,
             // scanline: vid.sprite_gen.scanlines[0].clone(),
+            detect_collisions: vid.detect_collisions,
+            collisions: SpriteCollisions::default(),
+            sprite_owner: [0; MAX_RESOLUTION_X],
             sprite_buffer: [RGBA12::TRANSPARENT.with_z(Z_SPRITE); MAX_RESOLUTION_X],
+            shadow_buffer: [false; MAX_RESOLUTION_X],
             bg_buffer: Self::generate_bg_color(0, vid),
         };
 
+        // Collision registers are cleared at the start of every frame, even if detection
+        // is disabled, so that they never report collisions from an older frame
+        vid.collisions.set(SpriteCollisions::default());
+
         // Pre-render first line (IRQ will be called inside pre_render_line)
         result.pre_render_line();
         result
@@ -96,6 +132,144 @@ impl<'a> PixelIter<'a> {
         self.y
     }
 
+    pub(crate) fn vid(&self) -> &'a VideoChip {
+        self.vid
+    }
+
+    /// Renders all remaining lines straight into a frame buffer, bypassing the per-pixel
+    /// Iterator. The buffer must hold at least width * height pixels. Works one line at a
+    /// time, so the line IRQ still runs normally.
+    pub fn render_frame(mut self, frame: &mut [RGBA32]) {
+        let width = self.vid.width() as usize;
+        assert!(
+            frame.len() >= width * self.vid.height() as usize,
+            err!("Frame buffer is smaller than the video resolution")
+        );
+        while self.y <= self.vid.max_y() {
+            let start = self.y as usize * width;
+            let line = &mut frame[start..start + width];
+            if self.color_math.is_active() {
+                for (x, pixel) in line.iter_mut().enumerate() {
+                    *pixel = self.composite_math(x);
+                }
+            } else {
+                let bg_color = self.bg_color;
+                for ((pixel, &sprite), &bg) in
+                    line.iter_mut().zip(&self.sprite_buffer[..width]).zip(&self.bg_buffer[..width])
+                {
+                    *pixel = RGBA32::from(Self::composite(sprite, bg, bg_color));
+                }
+            }
+            self.next_line();
+        }
+    }
+
+    /// Same as [PixelIter::render_frame], but writes raw RGBA bytes (4 per pixel),
+    /// ready to be uploaded as a texture.
+    pub fn render_frame_bytes(mut self, frame: &mut [u8]) {
+        let width = self.vid.width() as usize;
+        assert!(
+            frame.len() >= width * self.vid.height() as usize * 4,
+            err!("Frame buffer is smaller than the video resolution")
+        );
+        while self.y <= self.vid.max_y() {
+            let start = self.y as usize * width * 4;
+            let line = &mut frame[start..start + (width * 4)];
+            let use_math = self.color_math.is_active();
+            let bg_color = self.bg_color;
+            for (x, pixel) in line.chunks_exact_mut(4).enumerate() {
+                let color = if use_math {
+                    self.composite_math(x)
+                } else {
+                    RGBA32::from(Self::composite(
+                        self.sprite_buffer[x],
+                        self.bg_buffer[x],
+                        bg_color,
+                    ))
+                };
+                pixel[0] = color.r;
+                pixel[1] = color.g;
+                pixel[2] = color.b;
+                pixel[3] = color.a;
+            }
+            self.next_line();
+        }
+    }
+
+    /// Final compositing step of a single pixel.
+    #[inline(always)]
+    fn composite(sprite: RGBA12, bg: RGBA12, bg_color: RGBA12) -> RGBA12 {
+        // Compare z values: sprite has alpha and higher/equal z wins
+        if sprite.a() > 0 && sprite.z() >= bg.z() {
+            sprite
+        } else if bg.a() > 0 {
+            bg
+        } else {
+            bg_color.with_z(Z_BG)
+        }
+    }
+
+    /// Slower version of the compositing step, used when any ColorMath register is active.
+    #[inline]
+    fn composite_math(&self, x: usize) -> RGBA32 {
+        let sprite = self.sprite_buffer[x];
+        let bg = self.bg_buffer[x];
+        if self.math_hidden[x] {
+            return RGBA32::from(Self::composite(sprite, bg, self.bg_color));
+        }
+        let math = &self.color_math;
+        let bg_color = RGBA32::from(self.bg_color);
+
+        // BG tiles blend with the BG color. Crop color and BG color are used as is.
+        let under = if bg.a() == 0 {
+            bg_color
+        } else if bg.z() == Z_BG {
+            RGBA32::from(bg)
+        } else {
+            math.bg_blend.blend(RGBA32::from(bg), bg_color)
+        };
+
+        let color = if sprite.a() > 0 && sprite.z() >= bg.z() {
+            if self.shadow_buffer[x] {
+                ColorMath::shadow(under)
+            } else {
+                math.sprite_blend.blend(RGBA32::from(sprite), under)
+            }
+        } else {
+            under
+        };
+
+        math.apply_brightness(color)
+    }
+
+    /// Final color of a pixel in the current line.
+    #[inline]
+    pub(crate) fn line_pixel(&self, x: usize) -> RGBA32 {
+        if self.color_math.is_active() {
+            self.composite_math(x)
+        } else {
+            RGBA32::from(Self::composite(self.sprite_buffer[x], self.bg_buffer[x], self.bg_color))
+        }
+    }
+
+    /// True if neither a BG tile nor a sprite covers a pixel in the current line,
+    /// so that the BG color (or the crop color) is displayed.
+    #[inline]
+    pub(crate) fn is_backdrop(&self, x: usize) -> bool {
+        let sprite = self.sprite_buffer[x];
+        let bg = self.bg_buffer[x];
+        let sprite_visible = sprite.a() > 0 && sprite.z() >= bg.z();
+        !sprite_visible && (bg.a() == 0 || bg.z() == Z_BG)
+    }
+
+    #[inline]
+    pub(crate) fn next_line(&mut self) {
+        self.x = 0;
+        self.y += 1;
+        // Pre-render the new line (IRQ will be called inside pre_render_line)
+        self.pre_render_line();
+    }
+
     #[inline]
     fn call_line_irq(&mut self) {
         if let Some(func) = self.irq_y {
@@ -104,6 +278,47 @@ impl<'a> PixelIter<'a> {
         }
     }
 
+    /// Loads the raster effect values for the current line.
+    #[inline]
+    fn apply_raster(&mut self) {
+        let vid = self.vid;
+        let raster = &vid.raster;
+        let has_splits = raster.splits.iter().any(Option::is_some);
+        let mut scroll = vid.scroll;
+        if let Some(split) = raster.split_at(self.y) {
+            self.bg_map_bank = split.bg_map_bank;
+            self.bg_tile_bank = split.bg_tile_bank;
+            scroll = split.scroll.unwrap_or(vid.scroll);
+        } else if has_splits {
+            self.bg_map_bank = 0;
+            self.bg_tile_bank = vid.bg_tile_bank;
+        }
+        // The iterator also pre-renders the line after the last one, which may be past the tables
+        let line = self.y as usize;
+        if raster.scroll.enabled {
+            let offset = raster.scroll.lines.get(line).copied().unwrap_or_default();
+            scroll = Vec2 { x: scroll.x + offset.x, y: scroll.y + offset.y };
+        }
+        if raster.scroll.enabled || has_splits {
+            self.scroll_x = scroll.x;
+            self.scroll_y = scroll.y;
+        }
+        if raster.bg_colors.enabled
+            && let Some(&color) = raster.bg_colors.lines.get(line)
+        {
+            self.bg_color = color;
+        }
+    }
+
+    #[inline]
+    fn call_x_irq(&mut self, x: u16) {
+        if let Some(func) = self.irq_x {
+            self.x = x;
+            let bg_map = self.tilemaps[self.bg_map_bank as usize];
+            func(self, self.vid, &bg_map);
+        }
+    }
+
     #[inline]
     fn generate_bg_color(y: u16, vid: &VideoChip) -> [RGBA12; MAX_RESOLUTION_X] {
         if y < vid.view_top || y > vid.view_bottom {
@@ -115,6 +330,17 @@ impl<'a> PixelIter<'a> {
 
     #[inline]
     fn pre_render_line(&mut self) {
+        if self.has_windows {
+            for (span, window) in self.window_spans.iter_mut().zip(&self.vid.windows) {
+                *span = window.lines.get(self.y as usize).copied().unwrap_or_default();
+            }
+            self.math_hidden.fill(false);
+        }
+
+        if self.vid.raster.is_active() {
+            self.apply_raster();
+        }
+
         // Run Y IRQ before rendering the line
         self.call_line_irq();
 
@@ -145,7 +371,6 @@ impl<'a> PixelIter<'a> {
                 *ptr.add(x) = RGBA12::TRANSPARENT;
             }
         }
-        self.pre_render_sprites(width, view_start, view_end);
 
         // Fast fill non-viewport areas with crop_color
         unsafe {
@@ -164,40 +389,169 @@ impl<'a> PixelIter<'a> {
                 }
             }
         }
-        self.pre_render_background(width);
+
+        // With mosaic, every line in a block repeats the block's first line
+        let sprite_line = Mosaic::block_line(self.mosaic.sprites, self.y, self.vid.view_top);
+        let bg_line = Mosaic::block_line(self.mosaic.bg, self.y, self.vid.view_top);
+
+        // The horizontal IRQ splits the line in spans, and can modify the iterator
+        // before each span is rendered
+        let mut span_start = view_start;
+        loop {
+            let span_end = match self.irq_x {
+                Some(_) => self
+                    .irq_x_trigger
+                    .next(span_start as u16, self.scroll_x)
+                    .map_or(view_end, |x| (x as usize).min(view_end)),
+                None => view_end,
+            };
+            self.pre_render_span(sprite_line, bg_line, width, span_start, span_end);
+            if span_end >= view_end {
+                break;
+            }
+            span_start = span_end;
+            self.call_x_irq(span_start as u16);
+        }
+        self.x = 0;
+
+        if self.mosaic.sprites > 1 {
+            let sprites = self.mosaic.sprites;
+            Mosaic::repeat_blocks(sprites, &mut self.sprite_buffer, view_start, view_end);
+            Mosaic::repeat_blocks(sprites, &mut self.sprite_owner, view_start, view_end);
+            Mosaic::repeat_blocks(sprites, &mut self.shadow_buffer, view_start, view_end);
+        }
+        Mosaic::repeat_blocks(self.mosaic.bg, &mut self.bg_buffer, view_start, view_end);
+
+        let has_sprites = self.vid.sprite_gen.scanlines[sprite_line as usize].mask != 0;
+        if self.detect_collisions && has_sprites {
+            self.detect_bg_collisions(view_start, view_end);
+        }
+
+        if self.has_windows {
+            self.apply_windows(view_start, view_end);
+        }
+    }
+
+    /// Renders both layers between "view_start" and "view_end". The lines may differ
+    /// from the current line when using mosaic.
+    #[inline]
+    fn pre_render_span(
+        &mut self,
+        sprite_line: u16,
+        bg_line: u16,
+        width: u16,
+        view_start: usize,
+        view_end: usize,
+    ) {
+        self.pre_render_sprites(sprite_line, width, view_start, view_end);
+        // The BG renderer reads the current line, so it's temporarily replaced
+        let y = self.y;
+        self.y = bg_line;
+        self.pre_render_background(view_start, view_end);
+        self.y = y;
+    }
+
+    /// Hides the masked layers on either side of each enabled window.
+    #[inline]
+    fn apply_windows(&mut self, view_start: usize, view_end: usize) {
+        for i in 0..WINDOW_COUNT {
+            let window = &self.vid.windows[i];
+            if !window.enabled {
+                continue;
+            }
+            let span = self.window_spans[i];
+            let left = (span.left as usize).clamp(view_start, view_end);
+            let right = (span.right as usize).clamp(left, view_end);
+            let bg_color = self.bg_color.with_z(Z_BG);
+
+            let hide = |mask: WindowMask, func: &mut dyn FnMut(usize)| {
+                let ranges = match mask {
+                    WindowMask::Off => return,
+                    WindowMask::Inside => [left..right, 0..0],
+                    WindowMask::Outside => [view_start..left, right..view_end],
+                };
+                for x in ranges.into_iter().flatten() {
+                    func(x);
+                }
+            };
+            hide(window.bg, &mut |x| self.bg_buffer[x] = bg_color);
+            hide(window.sprites, &mut |x| self.sprite_buffer[x] = RGBA12::TRANSPARENT);
+            hide(window.color_math, &mut |x| self.math_hidden[x] = true);
+        }
+    }
+
+    /// Any visible sprite pixel over a BG tile pixel (instead of the BG color) is a collision.
+    #[inline]
+    fn detect_bg_collisions(&mut self, view_start: usize, view_end: usize) {
+        for x in view_start..view_end {
+            if self.sprite_buffer[x].a() > 0 && self.bg_buffer[x].z() != Z_BG {
+                self.sprite_collision(self.sprite_owner[x], None, x);
+            }
+        }
+    }
+
+    /// Updates the VideoChip collision registers, calls the collision IRQ
+    /// if any sprite involved is colliding for the first time.
+    #[inline]
+    fn sprite_collision(&mut self, sprite: u8, other: Option<u8>, x: usize) {
+        let is_new = match other {
+            Some(other) => {
+                let a = self.collisions.set_sprite(sprite);
+                let b = self.collisions.set_sprite(other);
+                a || b
+            },
+            None => self.collisions.set_bg(sprite),
+        };
+        if !is_new {
+            return;
+        }
+        self.vid.collisions.set(self.collisions);
+        if let Some(func) = self.vid.irq_collision {
+            func(self.vid, SpriteCollision { sprite, other, x: x as u16, y: self.y });
+        }
     }
 
     #[inline]
-    fn pre_render_sprites(&mut self, width: u16, view_start: usize, view_end: usize) {
+    fn pre_render_sprites(&mut self, line: u16, width: u16, view_start: usize, view_end: usize) {
         self.x = 0;
         // self.scanline = self.vid.sprite_gen.scanlines[self.y as usize].clone();
-        let scanline = &self.vid.sprite_gen.scanlines[self.y as usize];
+        let vid = self.vid;
+        let scanline = &vid.sprite_gen.scanlines[line as usize];
 
         // Early exit if no sprites or no viewport
         if scanline.mask == 0 {
             return;
         }
 
-        let line_y = self.y as i16;
+        let line_y = line as i16;
         let bank = self.tile_banks[self.fg_tile_bank as usize];
+        let color_bank = self.fg_color_bank.unwrap_or(self.fg_tile_bank);
+        let palette = &self.tile_banks[color_bank as usize].colors.palette;
+        let shadow_color = self.color_math.shadow_color;
 
         // Process sprites from back to front
         for n in (0..scanline.sprite_count as usize).rev() {
             let sprite_id = scanline.sprites[n] as usize;
-            let sprite = &self.vid.sprite_gen.sprites[sprite_id];
+            let sprite = &vid.sprite_gen.sprites[sprite_id];
 
             if sprite.flags.is_invisible() {
                 continue;
             }
 
+            // The bottom half of a tall sprite uses the next tile, which may not exist
+            if sprite.size == SpriteSize::Tall && sprite.id.0 as usize + 1 >= bank.tiles.capacity()
+            {
+                continue;
+            }
+
             let sprite_y = line_y - sprite.y;
-            if sprite_y < 0 || sprite_y >= TILE_SIZE as i16 {
+            if sprite_y < 0 || sprite_y >= sprite.size.height() {
                 continue;
             }
 
             // Calculate sprite bounds clamped to viewport
             let sprite_start = sprite.x.max(0) as usize;
-            let sprite_end = ((sprite.x + TILE_SIZE as i16).min(width as i16)) as usize;
+            let sprite_end = ((sprite.x + sprite.size.width()).min(width as i16)) as usize;
 
             // Clamp to viewport
             let start_x = sprite_start.max(view_start);
@@ -228,7 +582,9 @@ impl<'a> PixelIter<'a> {
                 continue;
             }
 
-            let tile = &bank.tiles.tiles[sprite.id.0 as usize];
+            // Priority among sprites is resolved first, by sprite order. The winning
+            // pixel then uses its own z value to composite against the BG.
+            let z_value = if sprite.flags.is_behind_bg() { Z_SPRITE_BEHIND } else { Z_SPRITE };
             // let color_mapping = sprite.color_mapping;
 
             // Render sprite pixels - only in active slots!
@@ -239,50 +595,194 @@ impl<'a> PixelIter<'a> {
                     continue;
                 }
 
-                // Skip if already has a sprite pixel
-                if self.sprite_buffer[x].a() > 0 {
+                // Skip if already has a sprite pixel, unless we need to detect collisions
+                let occupied = self.sprite_buffer[x].a() > 0;
+                if occupied && !self.detect_collisions {
                     continue;
                 }
 
                 let sprite_x = x as i16 - sprite.x;
 
-                // Using standard transform_coords. Consistency!
-                let (tx, ty) =
-                    sprite.flags.transform_coords(sprite_x as u8, sprite_y as u8, TILE_SIZE);
+                // Uses the standard transform_coords, adjusted for the sprite size
+                let (id, tx, ty) = sprite.tile_coords(sprite_x as u8, sprite_y as u8);
 
-                let pixel = tile.get_pixel(tx as u8, ty as u8);
-                let color_index = sprite.colors.get(pixel);
-                let color = bank.colors.palette[color_index as usize];
+                let color_index = bank.tiles.color_index(id, tx, ty, sprite.colors);
+                let color = palette[color_index as usize];
 
                 if color.a() > 0 {
-                    self.sprite_buffer[x] = color.with_z(Z_SPRITE);
+                    if occupied {
+                        self.sprite_collision(sprite_id as u8, Some(self.sprite_owner[x]), x);
+                    } else {
+                        self.sprite_buffer[x] = color.with_z(z_value);
+                        self.sprite_owner[x] = sprite_id as u8;
+                        self.shadow_buffer[x] = shadow_color == Some(color_index);
+                    }
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn pre_render_background(&mut self, view_start: usize, view_end: usize) {
+        let main = BgPlane {
+            enabled: true,
+            map_bank: self.bg_map_bank,
+            tile_bank: self.bg_tile_bank,
+            scroll: Vec2 { x: self.scroll_x, y: self.scroll_y },
+            wrap: self.wrap_bg,
+            priority: PlanePriority::AboveMain,
+        };
+
+        // Only pay for the extra planes if any is enabled
+        if self.bg_planes.iter().all(|plane| !plane.enabled) {
+            self.pre_render_main(main, view_start, view_end, true);
+            return;
+        }
+
+        // Planes behind the main map need the BG color underneath them,
+        // and the main map can't overwrite them with the BG color.
+        let has_back_planes = self
+            .bg_planes
+            .iter()
+            .any(|plane| plane.enabled && plane.priority == PlanePriority::BehindMain);
+        if has_back_planes {
+            let bg_color = self.bg_color.with_z(Z_BG);
+            for x in view_start..view_end {
+                self.bg_buffer[x] = bg_color;
+            }
+            self.pre_render_planes(PlanePriority::BehindMain, view_start, view_end);
+        }
+
+        self.pre_render_main(main, view_start, view_end, !has_back_planes);
+        self.pre_render_planes(PlanePriority::AboveMain, view_start, view_end);
+        self.pre_render_planes(PlanePriority::AboveSprites, view_start, view_end);
+    }
+
+    /// The main BG map is the only one that can use the affine mode.
+    #[inline]
+    fn pre_render_main(&mut self, main: BgPlane, view_start: usize, view_end: usize, base: bool) {
+        match self.affine {
+            Some(affine) => self.pre_render_affine(main, affine, view_start, view_end, base),
+            None => self.pre_render_plane(main, view_start, view_end, base),
+        }
+    }
+
+    /// Renders a BG plane sampling each pixel through an affine matrix. Slower than
+    /// the regular tile renderer, since every pixel requires its own tile lookup.
+    #[inline]
+    fn pre_render_affine(
+        &mut self,
+        plane: BgPlane,
+        affine: Affine,
+        view_start: usize,
+        view_end: usize,
+        base: bool,
+    ) {
+        self.x = 0;
+        let bg = self.tilemaps[plane.map_bank as usize];
+        let bank = self.tile_banks[plane.tile_bank as usize];
+        let palette = &bank.colors.palette;
+        let bg_color = self.bg_color.with_z(Z_BG);
+        let (frame_number, frame_rate) = (self.vid.frame_number, self.vid.frame_rate);
+
+        let bg_width = bg.width() as i32;
+        let bg_height = bg.height() as i32;
+        let bg_columns = bg.columns() as usize;
+        let (a, b) = (affine.a as i32, affine.b as i32);
+        let (c, d) = (affine.c as i32, affine.d as i32);
+        let origin_x = affine.origin.x as i32;
+        let origin_y = affine.origin.y as i32;
+
+        // Map coordinates of the first pixel in 8.8 fixed point. Moving one pixel
+        // to the right simply adds the first matrix column.
+        let rel_x = view_start as i32 + plane.scroll.x as i32 - origin_x;
+        let rel_y = self.y as i32 + plane.scroll.y as i32 - origin_y;
+        let mut map_x = (a * rel_x) + (b * rel_y) + (origin_x << 8);
+        let mut map_y = (c * rel_x) + (d * rel_y) + (origin_y << 8);
+
+        for x in view_start..view_end {
+            let mut px = map_x >> 8;
+            let mut py = map_y >> 8;
+            map_x += a;
+            map_y += c;
+
+            if plane.wrap {
+                px = px.rem_euclid(bg_width);
+                py = py.rem_euclid(bg_height);
+            } else if px < 0 || py < 0 || px >= bg_width || py >= bg_height {
+                if base {
+                    self.bg_buffer[x] = bg_color;
                 }
+                continue;
+            }
+
+            let cell_index = (py as usize / TILE_SIZE as usize * bg_columns)
+                + (px as usize / TILE_SIZE as usize);
+            let cell = bg.cells()[cell_index];
+            if cell.flags.is_invisible() {
+                if base {
+                    self.bg_buffer[x] = bg_color;
+                }
+                continue;
+            }
+
+            let (tx, ty) = cell.flags.transform_coords(
+                (px % TILE_SIZE as i32) as u8,
+                (py % TILE_SIZE as i32) as u8,
+                TILE_SIZE,
+            );
+            let id = bank.tiles.resolve_anim(cell.id, frame_number, frame_rate);
+            let color_index = bank.tiles.color_index(id, tx, ty, cell.colors);
+            let color = palette[color_index as usize];
+            if color.a() > 0 {
+                let z_value = if cell.flags.is_fg() { Z_BG_FOREGROUND } else { Z_BG_TILE };
+                self.bg_buffer[x] = color.with_z(z_value);
+            } else if base {
+                self.bg_buffer[x] = bg_color;
+            }
+        }
+    }
+
+    /// Renders all enabled planes with the desired priority, in index order.
+    #[inline]
+    fn pre_render_planes(&mut self, priority: PlanePriority, view_start: usize, view_end: usize) {
+        for i in 0..BG_PLANE_COUNT {
+            let plane = self.bg_planes[i];
+            if plane.enabled && plane.priority == priority {
+                self.pre_render_plane(plane, view_start, view_end, false);
             }
         }
     }
 
+    /// Renders a single BG plane into the BG buffer. The "base" plane fills transparent
+    /// and out-of-bounds pixels with the BG color, other planes simply skip them so that
+    /// the planes underneath remain visible.
     #[inline]
-    fn pre_render_background(&mut self, width: u16) {
+    fn pre_render_plane(&mut self, plane: BgPlane, view_start: usize, view_end: usize, base: bool) {
         // Reset x position for iteration
         self.x = 0;
-        let bg = self.tilemaps[self.bg_map_bank as usize];
+        let bg = self.tilemaps[plane.map_bank as usize];
         let line_y = self.y as i16;
-        let bank = self.tile_banks[self.bg_tile_bank as usize];
-
-        // Pre-calculate viewport bounds
-        let view_start = self.vid.view_left.max(0) as usize;
-        let view_end = self.vid.view_right.min(width) as usize;
+        let bank = self.tile_banks[plane.tile_bank as usize];
+        let (frame_number, frame_rate) = (self.vid.frame_number, self.vid.frame_rate);
+        let wrap = plane.wrap;
+        let z_tile = match plane.priority {
+            PlanePriority::AboveSprites => Z_BG_FOREGROUND,
+            _ => Z_BG_TILE,
+        };
 
         // Pre-calculate Y coordinates once
-        let bg_y_base = line_y + self.scroll_y;
+        let bg_y_base = line_y + plane.scroll.y;
         let bg_height = bg.height() as i16;
         let bg_width = bg.width() as i16;
 
-        // Check Y bounds once for entire line (when wrap_bg is false)
-        if !self.wrap_bg && (bg_y_base < 0 || bg_y_base >= bg_height) {
-            let bg_color = self.bg_color;
-            for x in view_start..view_end {
-                self.bg_buffer[x] = bg_color.with_z(Z_BG);
+        // Check Y bounds once for entire line (when wrap is false)
+        if !wrap && (bg_y_base < 0 || bg_y_base >= bg_height) {
+            if base {
+                let bg_color = self.bg_color;
+                for x in view_start..view_end {
+                    self.bg_buffer[x] = bg_color.with_z(Z_BG);
+                }
             }
             return;
         }
@@ -297,24 +797,28 @@ impl<'a> PixelIter<'a> {
 
         while x < view_end {
             // Calculate starting BG X coordinate
-            let bg_x_base = x as i16 + self.scroll_x;
+            let bg_x_base = x as i16 + plane.scroll.x;
 
             // Handle horizontal out of bounds
-            if !self.wrap_bg {
+            if !wrap {
                 if bg_x_base < 0 {
                     // Skip negative pixels
                     let skip = (-bg_x_base).min((view_end - x) as i16) as usize;
-                    let bg_color = self.bg_color;
-                    for i in 0..skip {
-                        self.bg_buffer[x + i] = bg_color.with_z(Z_BG);
+                    if base {
+                        let bg_color = self.bg_color;
+                        for i in 0..skip {
+                            self.bg_buffer[x + i] = bg_color.with_z(Z_BG);
+                        }
                     }
                     x += skip;
                     continue;
                 } else if bg_x_base >= bg_width {
                     // Fill rest with bg_color and exit
-                    let bg_color = self.bg_color;
-                    for i in x..view_end {
-                        self.bg_buffer[i] = bg_color.with_z(Z_BG);
+                    if base {
+                        let bg_color = self.bg_color;
+                        for i in x..view_end {
+                            self.bg_buffer[i] = bg_color.with_z(Z_BG);
+                        }
                     }
                     break;
                 }
@@ -328,52 +832,42 @@ impl<'a> PixelIter<'a> {
             let bg_map_index = (bg_row as usize * bg_columns) + bg_col as usize;
             let bg_cell = bg.cells()[bg_map_index];
             let bg_flags = bg_cell.flags;
-            let bg_tile_id = bg_cell.id.0 as usize;
             // let bg_color_mapping = bg_cell.color_mapping as usize;
 
-            // Skip invisible tiles - fill with bg_color instead
-            if bg_flags.is_invisible() {
-                let tile_pixels_remaining = (TILE_SIZE as usize) - tile_x_start as usize;
-                let viewport_pixels_remaining = view_end - x;
-                let pixels_to_process = tile_pixels_remaining.min(viewport_pixels_remaining);
+            // Calculate pixels to process in this tile
+            let tile_pixels_remaining = (TILE_SIZE as usize) - tile_x_start as usize;
+            let viewport_pixels_remaining = view_end - x;
+            let pixels_to_process = tile_pixels_remaining.min(viewport_pixels_remaining);
 
-                let pixels_to_process = if !self.wrap_bg && bg_x_base >= 0 {
-                    let max_x = (bg_width - bg_x_base) as usize;
-                    pixels_to_process.min(max_x)
-                } else {
-                    pixels_to_process
-                };
+            // Additional constraint for wrap=false
+            let pixels_to_process = if !wrap && bg_x_base >= 0 {
+                let max_x = (bg_width - bg_x_base) as usize;
+                pixels_to_process.min(max_x)
+            } else {
+                pixels_to_process
+            };
 
-                let bg_color = self.bg_color;
-                for i in 0..pixels_to_process {
-                    self.bg_buffer[x + i] = bg_color.with_z(Z_BG);
+            // Skip invisible tiles - fill with bg_color instead
+            if bg_flags.is_invisible() {
+                if base {
+                    let bg_color = self.bg_color;
+                    for i in 0..pixels_to_process {
+                        self.bg_buffer[x + i] = bg_color.with_z(Z_BG);
+                    }
                 }
                 x += pixels_to_process;
                 continue;
             }
 
-            // Get the tile cluster for this row
-            let tile = &bank.tiles.tiles[bg_tile_id];
-            let bg_cluster = Cluster::from_tile(&tile.clusters, bg_flags, tile_y, TILE_SIZE);
+            // Get the bank color indices for this row
+            let id = bank.tiles.resolve_anim(bg_cell.id, frame_number, frame_rate);
+            let row_colors = bank.tiles.row_colors(id, bg_flags, tile_y, bg_cell.colors);
 
             // Pre-fetch palette data
             let palette = &bank.colors.palette;
-            let is_fg = bg_flags.is_fg();
+            let z_value = if bg_flags.is_fg() { Z_BG_FOREGROUND } else { z_tile };
             let bg_color = self.bg_color;
 
-            // Calculate pixels to process in this tile
-            let tile_pixels_remaining = (TILE_SIZE as usize) - tile_x_start as usize;
-            let viewport_pixels_remaining = view_end - x;
-            let pixels_to_process = tile_pixels_remaining.min(viewport_pixels_remaining);
-
-            // Additional constraint for wrap_bg=false
-            let pixels_to_process = if !self.wrap_bg && bg_x_base >= 0 {
-                let max_x = (bg_width - bg_x_base) as usize;
-                pixels_to_process.min(max_x)
-            } else {
-                pixels_to_process
-            };
-
             // Process pixels in batch
             unsafe {
                 let dst_ptr = self.bg_buffer.as_mut_ptr().add(x);
@@ -388,20 +882,16 @@ impl<'a> PixelIter<'a> {
                     let base_idx = chunk * 4;
                     for i in 0..4 {
                         let tile_x = tile_x_start + (base_idx + i) as u8;
-                        let color_idx = bg_cluster.get_subpixel(tile_x % PIXELS_PER_CLUSTER);
-                        let mapped_idx = bg_cell.colors.get(color_idx);
+                        let mapped_idx = row_colors[(tile_x % PIXELS_PER_CLUSTER) as usize];
                         // let mapped_idx = bank.colors[remap_id][color_idx] as usize;
                         // let mapped_idx = bank.colors.mapping[remap_id][color_idx] as usize;
                         let color = palette[mapped_idx as usize];
 
-                        let final_color = if color.a() > 0 {
-                            let z_value = if is_fg { Z_BG_FOREGROUND } else { Z_BG_TILE };
-                            color.with_z(z_value)
-                        } else {
-                            bg_color.with_z(Z_BG)
-                        };
-
-                        *dst_ptr.add(base_idx + i) = final_color;
+                        if color.a() > 0 {
+                            *dst_ptr.add(base_idx + i) = color.with_z(z_value);
+                        } else if base {
+                            *dst_ptr.add(base_idx + i) = bg_color.with_z(Z_BG);
+                        }
                     }
                 }
 
@@ -409,19 +899,15 @@ impl<'a> PixelIter<'a> {
                 for i in 0..remainder {
                     let idx = chunks * 4 + i;
                     let tile_x = tile_x_start + idx as u8;
-                    let color_idx = bg_cluster.get_subpixel(tile_x % PIXELS_PER_CLUSTER);
-                    let mapped_idx = bg_cell.colors.get(color_idx);
+                    let mapped_idx = row_colors[(tile_x % PIXELS_PER_CLUSTER) as usize];
                     // let mapped_idx = bank.colors.mapping[remap_id][color_idx] as usize;
                     let color = palette[mapped_idx as usize];
 
-                    let final_color = if color.a() > 0 {
-                        let z_value = if is_fg { Z_BG_FOREGROUND } else { Z_BG_TILE };
-                        color.with_z(z_value)
-                    } else {
-                        bg_color.with_z(Z_BG)
-                    };
-
-                    *dst_ptr.add(idx) = final_color;
+                    if color.a() > 0 {
+                        *dst_ptr.add(idx) = color.with_z(z_value);
+                    } else if base {
+                        *dst_ptr.add(idx) = bg_color.with_z(Z_BG);
+                    }
                 }
             }
 
@@ -446,26 +932,18 @@ impl<'a> Iterator for PixelIter<'a> {
         let bg = self.bg_buffer[self.x as usize];
 
         // results
-        let color = RGBA32::from(
-            // Compare z values: sprite has alpha and higher/equal z wins
-            if sprite.a() > 0 && sprite.z() >= bg.z() {
-                sprite
-            } else if bg.a() > 0 {
-                bg
-            } else {
-                self.bg_color.with_z(Z_BG)
-            },
-        );
+        let color = if self.color_math.is_active() {
+            self.composite_math(self.x as usize)
+        } else {
+            RGBA32::from(Self::composite(sprite, bg, self.bg_color))
+        };
 
         // Increment screen position
         self.x += 1;
 
         // Check if we need to go to the next line
         if self.x == self.vid.width() {
-            self.x = 0;
-            self.y += 1;
-            // Pre-render the new line (IRQ will be called inside pre_render_line)
-            self.pre_render_line();
+            self.next_line();
         }
 
         // Return the pixel color
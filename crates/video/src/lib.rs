@@ -1,8 +1,23 @@
 #![no_std]
 
+#[cfg(test)]
+extern crate std;
+
+mod affine;
+pub use affine::*;
+
 mod bank;
 pub use bank::*;
 
+mod bank_checkpoint;
+pub use bank_checkpoint::*;
+
+mod bg_plane;
+pub use bg_plane::*;
+
+mod bitmap;
+pub use bitmap::*;
+
 mod bank_tiles;
 pub use bank_tiles::*;
 
@@ -18,26 +33,63 @@ pub use color::*;
 mod cluster;
 pub use cluster::*;
 
+mod collision;
+pub use collision::*;
+
+mod color_cycle;
+pub use color_cycle::*;
+
+mod color_math;
+pub use color_math::*;
+
 mod error;
 
+mod irq_trigger;
+pub use irq_trigger::*;
+
 mod iter;
 pub use iter::*;
 
+mod mosaic;
+pub use mosaic::*;
+
+mod object;
+pub use object::*;
+
+mod overlay;
+pub use overlay::*;
+
 mod palette;
 pub use palette::*;
 
+mod raster;
+pub use raster::*;
+
+mod save_state;
+pub use save_state::*;
+
 mod sprite;
 use sprite::*;
+pub use sprite::{SpriteSize, SpriteStats};
 
 mod tile;
 pub use tile::*;
 
+mod tile_anim;
+pub use tile_anim::*;
+
+mod tile_dedup;
+pub use tile_dedup::*;
+
 mod tilemap;
 pub use tilemap::*;
 
 mod tilemap_ref;
 pub use tilemap_ref::*;
 
+mod tilemap_stream;
+pub use tilemap_stream::*;
+
 mod tile_flags;
 pub use tile_flags::*;
 
@@ -47,8 +99,14 @@ pub use tile_flags::*;
 mod video_chip;
 pub use video_chip::*;
 
+mod window;
+pub use window::*;
+
 pub use tato_math as math;
 
+#[cfg(test)]
+mod tests;
+
 /// A callback used to modify the iterator, called once on every new scanline.
 /// The parameters are:
 /// - Mutable reference to the iterator
@@ -56,6 +114,10 @@ pub use tato_math as math;
 /// - Read only reference to the current tilemap
 pub type VideoIRQ = fn(&mut PixelIter, &VideoChip, &TilemapRef);
 
+/// A callback called during rendering when a sprite collides for the first time in a frame,
+/// either with another sprite or with the BG. Requires "VideoChip::detect_collisions".
+pub type CollisionIRQ = fn(&VideoChip, SpriteCollision);
+
 // -------------------------------- Constants --------------------------------
 
 /// Maximum number of video scanlines
@@ -65,6 +127,9 @@ pub const MAX_RESOLUTION_Y: usize = 240;
 /// Maximum number of simultaneous sprites on a single frame
 pub const MAX_SPRITES: usize = 256;
 
+/// Capacity of the retained object table. Multi-tile objects use one sprite per tile.
+pub const OBJECT_COUNT: usize = 128;
+
 /// Limits how many sprites can be visible in a single video scanline. Also affects
 /// the memory amount used by the videochip, since more sprites per line need more buffer space.
 pub const SPRITES_PER_LINE: usize = 16;
@@ -77,6 +142,12 @@ pub const SLOTS_PER_LINE: usize = 16;
 /// so must be 256 or less.
 pub const TILE_COUNT: usize = 256;
 
+/// Maximum number of animated tiles per tile bank.
+pub const TILE_ANIM_COUNT: usize = 16;
+
+/// Maximum number of frames in a single tile animation.
+pub const TILE_ANIM_FRAMES: usize = 8;
+
 /// Determines the X and Y size used by every tile.
 pub const TILE_SIZE: u8 = 8;
 
@@ -86,9 +157,13 @@ pub const TILE_PIXEL_COUNT: usize = TILE_SIZE as usize * TILE_SIZE as usize;
 /// The number of pixel clusters in a tile.
 pub const TILE_CLUSTER_COUNT: usize = TILE_PIXEL_COUNT / PIXELS_PER_CLUSTER as usize;
 
-/// Number of colors per palette (applies to FG and BG palette, 32 colors total)
+/// Number of colors per ColorBank. Sprites can use a different bank's colors than the BG
+/// (see "VideoChip::fg_color_bank"), for 32 colors total.
 pub const COLORS_PER_PALETTE: u8 = 16;
 
+/// Maximum number of color cycles per color bank.
+pub const COLOR_CYCLE_COUNT: usize = 4;
+
 /// Maximu number of color palette mappings
 pub const COLOR_MAPPING_COUNT:u8 = 16;
 
@@ -96,4 +171,28 @@ pub const COLOR_MAPPING_COUNT:u8 = 16;
 pub const BG_LEN: usize = 1024;
 
 pub const BG_BANK_COUNT: usize = 4;
+
+/// Maximum depth of each bank's checkpoint stack.
+pub const CHECKPOINT_COUNT: usize = 32;
+
+/// Number of additional BG planes that can be composited with the main BG map.
+pub const BG_PLANE_COUNT: usize = 3;
+
+/// Maximum number of fixed positions that trigger the horizontal IRQ.
+pub const IRQ_X_COUNT: usize = 8;
+
+/// Number of general purpose values available to the IRQs.
+pub const IRQ_CONTEXT_LEN: usize = 8;
+
+/// Maximum number of split regions in the raster effects.
+pub const RASTER_SPLIT_COUNT: usize = 4;
+
+/// Largest block size, in pixels, used by the mosaic effect.
+pub const MOSAIC_MAX_SIZE: u8 = 16;
+
+/// Format version stored in every save state. States from other versions can't be loaded.
+pub const SAVE_STATE_VERSION: u16 = 2;
+
+/// Number of windows that can mask layers.
+pub const WINDOW_COUNT: usize = 2;
 pub const BANK_COUNT: usize = 4;
@@ -0,0 +1,56 @@
+use crate::*;
+
+/// Mosaic "register", renders the BG and sprite layers in square blocks of pixels that
+/// take the color of the block's top-left pixel. Block sizes go from 1 (no mosaic) to
+/// MOSAIC_MAX_SIZE, larger values are clamped. Blocks are aligned to the top-left
+/// corner of the screen, and can be modified per scanline by the line IRQ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Mosaic {
+    pub bg: u8,
+    pub sprites: u8,
+}
+
+impl Mosaic {
+    pub const fn new(bg: u8, sprites: u8) -> Self {
+        Self { bg, sprites }
+    }
+
+    /// Same block size on both layers.
+    pub const fn all(size: u8) -> Self {
+        Self { bg: size, sprites: size }
+    }
+
+    /// False if no layer uses blocks larger than 1 pixel.
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.bg > 1 || self.sprites > 1
+    }
+
+    /// Block size in pixels, clamped to the valid range.
+    #[inline]
+    pub(crate) fn block(size: u8) -> u16 {
+        size.clamp(1, MOSAIC_MAX_SIZE) as u16
+    }
+
+    /// First scanline of the block that contains line "y". Blocks cut by the
+    /// viewport start at its "top" line instead.
+    #[inline]
+    pub(crate) fn block_line(size: u8, y: u16, top: u16) -> u16 {
+        let block = Self::block(size);
+        (y - (y % block)).max(top)
+    }
+
+    /// Copies the first pixel of each block over the rest of the block, in a single line.
+    #[inline]
+    pub(crate) fn repeat_blocks<T: Copy>(size: u8, line: &mut [T], start: usize, end: usize) {
+        let block = Self::block(size) as usize;
+        if block == 1 {
+            return;
+        }
+        for x in start..end {
+            // Blocks cut by the viewport use its first pixel instead
+            let first = (x - (x % block)).max(start);
+            line[x] = line[first];
+        }
+    }
+}
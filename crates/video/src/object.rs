@@ -0,0 +1,135 @@
+use crate::*;
+
+/// Handle to an object in the [ObjectTable]. Becomes invalid (and may be reused)
+/// once the object is removed.
+#[derive(Debug, Clone, Copy, Eq, PartialOrd, Ord, PartialEq, Hash, Default)]
+pub struct ObjectID(pub u8);
+
+/// What an object draws.
+#[derive(Debug, Clone, Copy)]
+pub enum ObjectGfx {
+    /// A single tile.
+    Tile(TileID),
+    /// A multi-tile sprite. Needs a 'static lifetime since the object outlives the frame,
+    /// which is the case for generated assets (i.e. animation strip frames).
+    Tilemap(TilemapRef<'static>),
+}
+
+/// A retained sprite. Unlike "VideoChip::draw_sprite", objects persist across frames
+/// and are submitted automatically by "VideoChip::frame_finish".
+#[derive(Debug, Clone, Copy)]
+pub struct Object {
+    pub x: i16,
+    pub y: i16,
+    pub gfx: ObjectGfx,
+    /// Flip and "behind BG" flags. Rotation only applies to single tiles.
+    pub flags: TileFlags,
+    /// Palette used by single tiles. Overrides the cell palettes in tilemaps, if present.
+    pub colors: Option<Palette>,
+    /// Sprite size mode used by single tiles.
+    pub size: SpriteSize,
+    /// Objects with higher priority are drawn in front of the ones with lower priority.
+    /// If equal, the object in the higher slot is in front.
+    pub priority: u8,
+    /// Invisible objects keep their slot, but aren't submitted.
+    pub visible: bool,
+}
+
+impl Object {
+    pub const fn tile(x: i16, y: i16, id: TileID) -> Self {
+        Self {
+            x,
+            y,
+            gfx: ObjectGfx::Tile(id),
+            flags: TileFlags(0),
+            colors: None,
+            size: SpriteSize::Normal,
+            priority: 0,
+            visible: true,
+        }
+    }
+
+    pub const fn tilemap(x: i16, y: i16, map: TilemapRef<'static>) -> Self {
+        Self { gfx: ObjectGfx::Tilemap(map), ..Self::tile(x, y, TileID(0)) }
+    }
+}
+
+/// Fixed capacity table of retained objects (a.k.a. OAM).
+#[derive(Debug, Clone)]
+pub struct ObjectTable {
+    objects: [Option<Object>; OBJECT_COUNT],
+    count: u16,
+}
+
+impl Default for ObjectTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ObjectTable {
+    pub const fn new() -> Self {
+        Self { objects: [None; OBJECT_COUNT], count: 0 }
+    }
+
+    /// Stores an object in the first free slot. Returns None if the table is full.
+    pub fn add(&mut self, object: Object) -> Option<ObjectID> {
+        let index = self.objects.iter().position(|slot| slot.is_none())?;
+        self.objects[index] = Some(object);
+        self.count += 1;
+        Some(ObjectID(index as u8))
+    }
+
+    /// Removes an object, returns it if the handle was valid.
+    pub fn remove(&mut self, id: ObjectID) -> Option<Object> {
+        let removed = self.objects.get_mut(id.0 as usize)?.take();
+        if removed.is_some() {
+            self.count -= 1;
+        }
+        removed
+    }
+
+    pub fn get(&self, id: ObjectID) -> Option<&Object> {
+        self.objects.get(id.0 as usize)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: ObjectID) -> Option<&mut Object> {
+        self.objects.get_mut(id.0 as usize)?.as_mut()
+    }
+
+    pub fn clear(&mut self) {
+        self.objects = [None; OBJECT_COUNT];
+        self.count = 0;
+    }
+
+    /// Number of live objects.
+    pub fn count(&self) -> usize {
+        self.count as usize
+    }
+
+    /// Iterates all live objects, in slot order. Useful for debuggers.
+    pub fn iter(&self) -> impl Iterator<Item = (ObjectID, &Object)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.as_ref().map(|object| (ObjectID(i as u8), object)))
+    }
+
+    /// Visible object slots, sorted in drawing order (lowest priority first).
+    /// Returns the array and how many entries are valid.
+    pub(crate) fn draw_order(&self) -> ([u8; OBJECT_COUNT], usize) {
+        let mut order = [0u8; OBJECT_COUNT];
+        let mut len = 0;
+        for (id, object) in self.iter() {
+            if object.visible {
+                order[len] = id.0;
+                len += 1;
+            }
+        }
+        order[..len].sort_unstable_by_key(|&i| {
+            let priority = self.objects[i as usize].map_or(0, |object| object.priority);
+            (priority, i)
+        });
+        (order, len)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::*;
+
+/// Determines which pixels of the front layer let the back layer show through.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Transparency {
+    /// Pixels not covered by BG tiles or sprites, where the BG color (or the crop color)
+    /// would be displayed.
+    #[default]
+    Backdrop,
+    /// Pixels with this exact output color.
+    ColorKey(RGBA32),
+    /// The front layer hides the back layer entirely, wherever they overlap.
+    Opaque,
+}
+
+impl Transparency {
+    #[inline]
+    fn is_transparent(self, color: RGBA32, backdrop: bool) -> bool {
+        match self {
+            Transparency::Backdrop => backdrop,
+            Transparency::ColorKey(key) => color == key,
+            Transparency::Opaque => false,
+        }
+    }
+}
+
+/// How a second VideoChip (the overlay) is composited with the main one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Overlay {
+    /// Position of the overlay's top-left corner on the main screen.
+    pub x: i16,
+    pub y: i16,
+    /// Applies to whichever layer is in front.
+    pub transparency: Transparency,
+    /// If false, the overlay is displayed behind the main chip instead.
+    pub in_front: bool,
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            transparency: Transparency::Backdrop,
+            in_front: true,
+        }
+    }
+}
+
+/// Composites the output of two VideoChips, i.e. a scrolling game layer and a HUD that
+/// never scrolls. Each chip keeps its own resolution, scroll, sprites, banks and IRQs.
+/// The result has the main chip's resolution, and the overlay is clipped to it.
+#[derive(Debug, Clone)]
+pub struct OverlayIter<'a> {
+    main: PixelIter<'a>,
+    overlay: PixelIter<'a>,
+    settings: Overlay,
+    x: u16,
+    // False if the current main line doesn't overlap the overlay
+    overlay_visible: bool,
+}
+
+impl<'a> OverlayIter<'a> {
+    /// Takes the iterators of both chips, i.e. from [VideoChip::iter_pixels].
+    pub fn new(main: PixelIter<'a>, overlay: PixelIter<'a>, settings: Overlay) -> Self {
+        let mut result = Self { main, overlay, settings, x: 0, overlay_visible: false };
+        result.sync_overlay();
+        result
+    }
+
+    /// Renders the whole frame into a caller-provided RGBA32 buffer with the main chip's
+    /// resolution, one line at a time.
+    pub fn render_frame(mut self, frame: &mut [RGBA32]) {
+        let vid = self.main.vid();
+        let width = vid.width() as usize;
+        assert!(
+            frame.len() >= width * vid.height() as usize,
+            err!("Frame buffer is smaller than the video resolution")
+        );
+        while self.main.y() <= vid.max_y() {
+            let start = self.main.y() as usize * width;
+            for (x, pixel) in frame[start..start + width].iter_mut().enumerate() {
+                *pixel = self.pixel(x);
+            }
+            self.next_line();
+        }
+    }
+
+    /// Advances the overlay until it reaches the line displayed in the current main line.
+    fn sync_overlay(&mut self) {
+        let line = self.main.y() as i32 - self.settings.y as i32;
+        let overlay_vid = self.overlay.vid();
+        self.overlay_visible = line >= 0 && line <= overlay_vid.max_y() as i32;
+        while self.overlay_visible && (self.overlay.y() as i32) < line {
+            self.overlay.next_line();
+        }
+    }
+
+    fn next_line(&mut self) {
+        self.x = 0;
+        self.main.next_line();
+        self.sync_overlay();
+    }
+
+    #[inline]
+    fn pixel(&self, x: usize) -> RGBA32 {
+        let main = (self.main.line_pixel(x), self.main.is_backdrop(x));
+        let overlay_x = x as i32 - self.settings.x as i32;
+        let overlay_width = self.overlay.vid().width() as i32;
+        if !self.overlay_visible || overlay_x < 0 || overlay_x >= overlay_width {
+            return main.0;
+        }
+        let overlay_x = overlay_x as usize;
+        let overlay = (self.overlay.line_pixel(overlay_x), self.overlay.is_backdrop(overlay_x));
+        let (front, back) = if self.settings.in_front { (overlay, main) } else { (main, overlay) };
+        if self.settings.transparency.is_transparent(front.0, front.1) { back.0 } else { front.0 }
+    }
+}
+
+impl<'a> Iterator for OverlayIter<'a> {
+    type Item = RGBA32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let vid = self.main.vid();
+        if self.main.y() > vid.max_y() {
+            return None;
+        }
+        let color = self.pixel(self.x as usize);
+        self.x += 1;
+        if self.x == vid.width() {
+            self.next_line();
+        }
+        Some(color)
+    }
+}
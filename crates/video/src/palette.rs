@@ -1,3 +1,5 @@
+use crate::PaletteRemap;
+
 #[derive(Clone, Default, Copy, PartialEq, Hash)]
 pub struct Palette(pub u16);
 
@@ -31,6 +33,16 @@ impl Palette {
         }
     }
 
+    /// Returns the palette with every bank color replaced using a remap table,
+    /// i.e. the one returned when appending a bank's colors to a different bank.
+    pub fn remap(&self, remap: &PaletteRemap) -> Self {
+        let mut result = *self;
+        for slot in 0..4 {
+            result.set(slot, remap[self.get(slot) as usize]);
+        }
+        result
+    }
+
     pub fn set(&mut self, slot: u8, value: u8) {
         assert!(slot < 4, "Palette: Max index is 3, {} was provided", slot);
         assert!(value < 16, "Palette: Max slot value is 15, {} was provided", value);
@@ -0,0 +1,165 @@
+use crate::*;
+use tato_math::{FloatTrig, Vec2};
+
+/// Offsets added to the main BG scroll on every line, i.e. for wavy water or heat haze.
+#[derive(Debug, Clone)]
+pub struct ScrollTable {
+    pub enabled: bool,
+    /// Offset for every scanline. All lines start at zero.
+    pub lines: [Vec2<i16>; MAX_RESOLUTION_Y],
+}
+
+impl Default for ScrollTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScrollTable {
+    pub const fn new() -> Self {
+        Self {
+            enabled: false,
+            lines: [Vec2 { x: 0, y: 0 }; MAX_RESOLUTION_Y],
+        }
+    }
+
+    /// Resets every line to zero.
+    pub fn clear(&mut self) {
+        self.lines = [Vec2 { x: 0, y: 0 }; MAX_RESOLUTION_Y];
+    }
+
+    /// Sets the offset of a single line.
+    pub fn set_line(&mut self, y: u16, x_offset: i16, y_offset: i16) {
+        if let Some(line) = self.lines.get_mut(y as usize) {
+            *line = Vec2 { x: x_offset, y: y_offset };
+        }
+    }
+
+    /// Fills lines "top" to "bottom" (exclusive) with a horizontal sine wave. "period" is the
+    /// wave length in lines, and "phase" shifts it, i.e. advance it every frame to animate.
+    pub fn set_wave(&mut self, top: u16, bottom: u16, amplitude: f32, period: f32, phase: f32) {
+        if period == 0.0 {
+            return;
+        }
+        let bottom = (bottom as usize).min(MAX_RESOLUTION_Y);
+        for y in (top as usize)..bottom {
+            let angle = ((y as f32 + phase) / period) * core::f32::consts::TAU;
+            self.lines[y].x = (FloatTrig::sin(angle) * amplitude) as i16;
+        }
+    }
+}
+
+/// Per line BG color, i.e. for sky gradients.
+#[derive(Debug, Clone)]
+pub struct ColorTable {
+    pub enabled: bool,
+    /// BG color for every scanline.
+    pub lines: [RGBA12; MAX_RESOLUTION_Y],
+}
+
+impl Default for ColorTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ColorTable {
+    pub const fn new() -> Self {
+        Self { enabled: false, lines: [RGBA12::BLACK; MAX_RESOLUTION_Y] }
+    }
+
+    /// Sets the color of a single line.
+    pub fn set_line(&mut self, y: u16, color: RGBA12) {
+        if let Some(line) = self.lines.get_mut(y as usize) {
+            *line = color;
+        }
+    }
+
+    /// Fills lines "top" to "bottom" (exclusive) with a gradient. Since each channel
+    /// only has 8 levels, the gradient is made of bands.
+    pub fn set_gradient(&mut self, top: u16, bottom: u16, from: RGBA12, to: RGBA12) {
+        let bottom = bottom.min(MAX_RESOLUTION_Y as u16);
+        if top >= bottom {
+            return;
+        }
+        // Rounds to the nearest level, the last line reaches the target color
+        let last = ((bottom - top) as i32 - 1).max(1);
+        let mix = |a: u8, b: u8, step: i32| {
+            let delta = (b as i32 - a as i32) * step;
+            (a as i32 + (delta * 2 + last).div_euclid(last * 2)) as u8
+        };
+        for y in top..bottom {
+            let step = (y - top) as i32;
+            let color = RGBA12::new(
+                mix(from.r(), to.r(), step),
+                mix(from.g(), to.g(), step),
+                mix(from.b(), to.b(), step),
+            );
+            self.lines[y as usize] = color;
+        }
+    }
+}
+
+/// Switches the main BG map and tile bank from a given line down, i.e. to display a HUD
+/// below (or above) the game area using its own tilemap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RasterSplit {
+    /// First line of the region. The region ends where the next split starts.
+    pub line: u16,
+    pub bg_map_bank: u8,
+    pub bg_tile_bank: u8,
+    /// Main BG scroll used in this region. If None, the VideoChip scroll is used.
+    pub scroll: Option<Vec2<i16>>,
+}
+
+/// Table driven raster effects, applied by the renderer at the start of every line,
+/// before the line IRQ (which can still override them). Lines above the first split use
+/// the VideoChip's own bank and scroll values.
+#[derive(Debug, Clone)]
+pub struct RasterEffects {
+    pub scroll: ScrollTable,
+    pub bg_colors: ColorTable,
+    /// Regions can be defined in any order.
+    pub splits: [Option<RasterSplit>; RASTER_SPLIT_COUNT],
+}
+
+impl Default for RasterEffects {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RasterEffects {
+    pub const fn new() -> Self {
+        Self {
+            scroll: ScrollTable::new(),
+            bg_colors: ColorTable::new(),
+            splits: [None; RASTER_SPLIT_COUNT],
+        }
+    }
+
+    /// Stores a split in the first free slot. Returns false if every slot is in use.
+    pub fn add_split(&mut self, split: RasterSplit) -> bool {
+        let Some(slot) = self.splits.iter_mut().find(|slot| slot.is_none()) else {
+            return false;
+        };
+        *slot = Some(split);
+        true
+    }
+
+    pub fn clear_splits(&mut self) {
+        self.splits = [None; RASTER_SPLIT_COUNT];
+    }
+
+    /// False if no effect is enabled, so the renderer can skip them entirely.
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.scroll.enabled || self.bg_colors.enabled || self.splits.iter().any(Option::is_some)
+    }
+
+    /// The split region that contains line "y", if any.
+    #[inline]
+    pub fn split_at(&self, y: u16) -> Option<RasterSplit> {
+        self.splits.iter().flatten().filter(|split| split.line <= y).max_by_key(|s| s.line).copied()
+    }
+}
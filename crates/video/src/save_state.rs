@@ -0,0 +1,959 @@
+use crate::*;
+use tato_math::Vec2;
+
+/// Identifies a tato save state. Followed by the format version, the kind of value and
+/// the length of the data that follows the header.
+const MAGIC: [u8; 4] = *b"TATO";
+const HEADER_LEN: usize = 11;
+
+const INVALID: &str = "Invalid value in save state";
+
+/// Writes values in little endian order into a caller-provided buffer. Once the buffer is
+/// full, values are only counted, so [StateWriter::len] always reports the size needed.
+#[derive(Debug)]
+pub struct StateWriter<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> StateWriter<'a> {
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    /// Bytes written so far, including the ones that didn't fit.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// False if anything was written past the end of the buffer.
+    pub fn fits(&self) -> bool {
+        self.len <= self.buffer.len()
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        let end = self.len + bytes.len();
+        if let Some(dest) = self.buffer.get_mut(self.len..end) {
+            dest.copy_from_slice(bytes);
+        }
+        self.len = end;
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.write_bytes(&[value]);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i16(&mut self, value: i16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+}
+
+/// Reads values written by a [StateWriter], in the same order.
+#[derive(Debug)]
+pub struct StateReader<'a> {
+    buffer: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, cursor: 0 }
+    }
+
+    /// Bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.buffer.len() - self.cursor
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        let bytes =
+            self.buffer.get(self.cursor..self.cursor + len).ok_or("Save state is truncated")?;
+        self.cursor += len;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, &'static str> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(INVALID),
+        }
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, &'static str> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16, &'static str> {
+        Ok(i16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, &'static str> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, &'static str> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, &'static str> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+}
+
+/// Values that can be written to a binary save state and restored exactly, i.e. for save
+/// states, rewinding in debug builds, or capturing a frame to reproduce a rendering bug.
+/// Nothing is allocated, the caller provides the buffers.
+///
+/// Each state starts with a header that stores SAVE_STATE_VERSION, and states from
+/// other versions are rejected.
+pub trait SaveState {
+    /// Stored in the header, so that a state can't be loaded into a different type.
+    const KIND: u8;
+
+    /// Writes the state without a header.
+    fn write_state(&self, writer: &mut StateWriter);
+
+    /// Reads a state written by [SaveState::write_state].
+    fn read_state(&mut self, reader: &mut StateReader) -> Result<(), &'static str>;
+
+    /// Size of the save state in bytes, header included.
+    fn state_len(&self) -> usize {
+        let mut writer = StateWriter::new(&mut []);
+        self.write_state(&mut writer);
+        HEADER_LEN + writer.len()
+    }
+
+    /// Writes the header and the state into "buffer". Returns how many bytes were used.
+    fn save_state(&self, buffer: &mut [u8]) -> Result<usize, &'static str> {
+        let mut writer = StateWriter::new(buffer);
+        writer.write_bytes(&MAGIC);
+        writer.write_u16(SAVE_STATE_VERSION);
+        writer.write_u8(Self::KIND);
+        writer.write_u32(0);
+        self.write_state(&mut writer);
+        if !writer.fits() {
+            return Err("Buffer is too small for save state");
+        }
+        let len = writer.len();
+        let body_len = (len - HEADER_LEN) as u32;
+        writer.buffer[HEADER_LEN - 4..HEADER_LEN].copy_from_slice(&body_len.to_le_bytes());
+        Ok(len)
+    }
+
+    /// Restores a state written by [SaveState::save_state]. Returns how many bytes were read,
+    /// so that multiple states can be stored one after the other. Nothing is modified if
+    /// the state is rejected.
+    fn load_state(&mut self, buffer: &[u8]) -> Result<usize, &'static str> {
+        let mut reader = StateReader::new(buffer);
+        if reader.read_bytes(MAGIC.len())? != MAGIC {
+            return Err("Not a save state");
+        }
+        if reader.read_u16()? != SAVE_STATE_VERSION {
+            return Err("Unsupported save state version");
+        }
+        if reader.read_u8()? != Self::KIND {
+            return Err("Save state belongs to a different type");
+        }
+        let body_len = reader.read_u32()? as usize;
+        let mut reader = StateReader::new(reader.read_bytes(body_len)?);
+        self.read_state(&mut reader)?;
+        if reader.remaining() > 0 {
+            return Err("Save state is longer than expected");
+        }
+        Ok(HEADER_LEN + body_len)
+    }
+}
+
+/// The VideoChip registers, including the sprites submitted this frame and the collision
+/// registers. The IRQs and the object table aren't included, since they contain function
+/// pointers and tilemap references: they're left untouched when loading.
+impl SaveState for VideoChip {
+    const KIND: u8 = 1;
+
+    fn write_state(&self, writer: &mut StateWriter) {
+        // Resolution first, so that it can be validated before anything is modified
+        self.w.write(writer);
+        self.h.write(writer);
+        self.view_left.write(writer);
+        self.view_top.write(writer);
+        self.view_right.write(writer);
+        self.view_bottom.write(writer);
+        self.bg_color.write(writer);
+        self.crop_color.write(writer);
+        self.wrap_sprites.write(writer);
+        self.sprite_flicker.write(writer);
+        self.wrap_bg.write(writer);
+        self.scroll.write(writer);
+        self.affine.write(writer);
+        self.color_math.write(writer);
+        self.mosaic.write(writer);
+        self.frame_rate.write(writer);
+        self.irq_x_trigger.write(writer);
+        self.irq_context.write(writer);
+        self.raster.write(writer);
+        self.detect_collisions.write(writer);
+        self.fg_tile_bank.write(writer);
+        self.fg_color_bank.write(writer);
+        self.bg_tile_bank.write(writer);
+        self.bg_planes.write(writer);
+        self.windows.write(writer);
+        self.sprite_gen.write_state(writer);
+        self.collisions.get().write(writer);
+        self.frame_number.write(writer);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> Result<(), &'static str> {
+        let w = u16::read(reader)?;
+        let h = u16::read(reader)?;
+        if w == 0 || w as usize > MAX_RESOLUTION_X || h < 8 || h as usize > MAX_RESOLUTION_Y {
+            return Err("Invalid resolution in save state");
+        }
+        // Everything is decoded and validated before the chip is modified
+        let view_left = u16::read(reader)?;
+        let view_top = u16::read(reader)?;
+        let view_right = u16::read(reader)?;
+        let view_bottom = u16::read(reader)?;
+        if view_left > view_right || view_right > w || view_top > view_bottom || view_bottom > h {
+            return Err(INVALID);
+        }
+        let bg_color = RGBA12::read(reader)?;
+        let crop_color = RGBA12::read(reader)?;
+        let wrap_sprites = bool::read(reader)?;
+        let sprite_flicker = bool::read(reader)?;
+        let wrap_bg = bool::read(reader)?;
+        let scroll = Vec2::read(reader)?;
+        let affine = Option::read(reader)?;
+        let color_math = ColorMath::read(reader)?;
+        let mosaic = Mosaic::read(reader)?;
+        let frame_rate = u8::read(reader)?;
+        let irq_x_trigger = IrqTrigger::read(reader)?;
+        let irq_context = <[i32; IRQ_CONTEXT_LEN]>::read(reader)?;
+        let raster = RasterEffects::read(reader)?;
+        let detect_collisions = bool::read(reader)?;
+        let fg_tile_bank = u8::read(reader)?;
+        let fg_color_bank = Option::read(reader)?;
+        let bg_tile_bank = u8::read(reader)?;
+        let bg_planes = <[BgPlane; BG_PLANE_COUNT]>::read(reader)?;
+        let windows = <[Window; WINDOW_COUNT]>::read(reader)?;
+        let mut sprite_gen = SpriteGenerator::new();
+        sprite_gen.read_state(reader)?;
+        let collisions = SpriteCollisions::read(reader)?;
+        let frame_number = usize::read(reader)?;
+
+        // The renderer indexes the banks and maps directly
+        let tile_bank = |bank: u8| (bank as usize) < BANK_COUNT;
+        let map_bank = |bank: u8| (bank as usize) < BG_BANK_COUNT;
+        let banks_valid = tile_bank(fg_tile_bank)
+            && fg_color_bank.is_none_or(tile_bank)
+            && tile_bank(bg_tile_bank)
+            && bg_planes.iter().all(|plane| map_bank(plane.map_bank) && tile_bank(plane.tile_bank))
+            && raster
+                .splits
+                .iter()
+                .flatten()
+                .all(|split| map_bank(split.bg_map_bank) && tile_bank(split.bg_tile_bank));
+        if !banks_valid {
+            return Err(INVALID);
+        }
+
+        self.w = w;
+        self.h = h;
+        self.view_left = view_left;
+        self.view_top = view_top;
+        self.view_right = view_right;
+        self.view_bottom = view_bottom;
+        self.bg_color = bg_color;
+        self.crop_color = crop_color;
+        self.wrap_sprites = wrap_sprites;
+        self.sprite_flicker = sprite_flicker;
+        self.wrap_bg = wrap_bg;
+        self.scroll = scroll;
+        self.affine = affine;
+        self.color_math = color_math;
+        self.mosaic = mosaic;
+        self.frame_rate = frame_rate;
+        self.irq_x_trigger = irq_x_trigger;
+        self.irq_context = irq_context;
+        self.raster = raster;
+        self.detect_collisions = detect_collisions;
+        self.fg_tile_bank = fg_tile_bank;
+        self.fg_color_bank = fg_color_bank;
+        self.bg_tile_bank = bg_tile_bank;
+        self.bg_planes = bg_planes;
+        self.windows = windows;
+        self.sprite_gen = sprite_gen;
+        self.collisions.set(collisions);
+        self.frame_number = frame_number;
+        Ok(())
+    }
+}
+
+impl SaveState for SpriteGenerator {
+    const KIND: u8 = 2;
+
+    fn write_state(&self, writer: &mut StateWriter) {
+        self.sprite_count.write(writer);
+        self.dropped_sprites.write(writer);
+        self.overflowed_lines.write(writer);
+        self.flicker_offset.write(writer);
+        self.sprites.write(writer);
+        for line in &self.scanlines {
+            line.sprite_count.write(writer);
+            line.mask.write(writer);
+            line.sprites.write(writer);
+            line.dropped.write(writer);
+        }
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> Result<(), &'static str> {
+        let mut sprite_gen = SpriteGenerator::new();
+        sprite_gen.sprite_count = u8::read(reader)?;
+        sprite_gen.dropped_sprites = u16::read(reader)?;
+        sprite_gen.overflowed_lines = u16::read(reader)?;
+        sprite_gen.flicker_offset = u8::read(reader)?;
+        sprite_gen.sprites = <[SpriteEntry; MAX_SPRITES]>::read(reader)?;
+        for line in &mut sprite_gen.scanlines {
+            line.sprite_count = u8::read(reader)?;
+            if line.sprite_count as usize > SPRITES_PER_LINE {
+                return Err(INVALID);
+            }
+            line.mask = u16::read(reader)?;
+            line.sprites = <[u8; SPRITES_PER_LINE]>::read(reader)?;
+            line.dropped = u8::read(reader)?;
+        }
+        *self = sprite_gen;
+        Ok(())
+    }
+}
+
+impl SaveState for Bank {
+    const KIND: u8 = 3;
+
+    fn write_state(&self, writer: &mut StateWriter) {
+        let tiles = &self.tiles;
+        tiles.mode.write(writer);
+        tiles.head.write(writer);
+        tiles.tiles.write(writer);
+        for anim in &tiles.anims {
+            anim.write(writer);
+        }
+        tiles.anim_count.write(writer);
+        tiles.anim_lookup.write(writer);
+        tiles.checkpoints.write(writer);
+        tiles.checkpoint_count.write(writer);
+
+        let colors = &self.colors;
+        colors.palette.write(writer);
+        colors.palette_head.write(writer);
+        colors.cycles.write(writer);
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> Result<(), &'static str> {
+        // Everything is decoded and validated before the bank is modified
+        let mode = TileMode::read(reader)?;
+        let capacity = TILE_COUNT / mode.slots_per_tile();
+        let head = u16::read(reader)?;
+        if head as usize > capacity {
+            return Err(INVALID);
+        }
+        let tiles = <[Tile<2>; TILE_COUNT]>::read(reader)?;
+        let mut anims = self.tiles.anims;
+        for anim in &mut anims {
+            *anim = TileAnim::read(reader)?;
+        }
+        let anim_count = u8::read(reader)?;
+        let anim_lookup = <[u8; TILE_COUNT]>::read(reader)?;
+        let checkpoints = <[BankCheckpoint; CHECKPOINT_COUNT]>::read(reader)?;
+        let checkpoint_count = u8::read(reader)?;
+        let anim_overflow = anim_count as usize > TILE_ANIM_COUNT
+            || anim_lookup.iter().any(|&index| index > anim_count);
+        if anim_overflow || checkpoint_count as usize > CHECKPOINT_COUNT {
+            return Err(INVALID);
+        }
+        // Animations may only use tiles that fit in the bank, and the lookup must point
+        // every animated tile at its own animation.
+        let active = &anims[..anim_count as usize];
+        let out_of_range = |id: &TileID| id.0 as usize >= capacity;
+        if active.iter().any(|anim| {
+            out_of_range(&anim.tile) || anim.frames[..anim.len as usize].iter().any(out_of_range)
+        }) {
+            return Err(INVALID);
+        }
+        let lookup_mismatch = anim_lookup
+            .iter()
+            .enumerate()
+            .any(|(tile, &index)| index > 0 && active[index as usize - 1].tile.0 as usize != tile);
+        let lookup_count = anim_lookup.iter().filter(|&&index| index > 0).count();
+        if lookup_mismatch || lookup_count != active.len() {
+            return Err(INVALID);
+        }
+        if checkpoints[..checkpoint_count as usize].iter().any(|checkpoint| {
+            checkpoint.tiles as usize > capacity || checkpoint.colors > COLORS_PER_PALETTE
+        }) {
+            return Err(INVALID);
+        }
+
+        let palette = <[RGBA12; COLORS_PER_PALETTE as usize]>::read(reader)?;
+        let palette_head = u8::read(reader)?;
+        if palette_head > COLORS_PER_PALETTE {
+            return Err(INVALID);
+        }
+        let cycles = <[Option<ColorCycle>; COLOR_CYCLE_COUNT]>::read(reader)?;
+
+        let bank = &mut self.tiles;
+        bank.mode = mode;
+        bank.head = head;
+        bank.tiles = tiles;
+        bank.anims = anims;
+        bank.anim_count = anim_count;
+        bank.anim_lookup = anim_lookup;
+        bank.checkpoints = checkpoints;
+        bank.checkpoint_count = checkpoint_count;
+        // Not stored, since a restored version could match one cached for different tiles
+        bank.version = bank.version.wrapping_add(1);
+        self.colors.palette = palette;
+        self.colors.palette_head = palette_head;
+        self.colors.cycles = cycles;
+        Ok(())
+    }
+}
+
+/// Only the cells within "columns * rows" are stored. The rest are cleared when loading.
+impl<const CELL_COUNT: usize> SaveState for Tilemap<CELL_COUNT> {
+    const KIND: u8 = 4;
+
+    fn write_state(&self, writer: &mut StateWriter) {
+        self.columns.write(writer);
+        self.rows.write(writer);
+        for cell in &self.cells[..self.columns as usize * self.rows as usize] {
+            cell.write(writer);
+        }
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> Result<(), &'static str> {
+        let columns = u16::read(reader)?;
+        let rows = u16::read(reader)?;
+        let len = columns as usize * rows as usize;
+        if len > CELL_COUNT {
+            return Err("Save state tilemap is larger than this Tilemap");
+        }
+        // Cells are always valid, so only a truncated state could fail halfway through
+        if reader.remaining() < len * 4 {
+            return Err("Save state is truncated");
+        }
+        self.columns = columns;
+        self.rows = rows;
+        for cell in &mut self.cells[..len] {
+            *cell = Cell::read(reader)?;
+        }
+        self.cells[len..].fill(Cell::default());
+        Ok(())
+    }
+}
+
+// -------------------------------- Values --------------------------------
+
+/// A single value within a save state.
+pub(crate) trait StateValue: Sized {
+    fn write(&self, writer: &mut StateWriter);
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str>;
+}
+
+impl StateValue for u8 {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_u8(*self);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        reader.read_u8()
+    }
+}
+
+impl StateValue for bool {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_bool(*self);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        reader.read_bool()
+    }
+}
+
+impl StateValue for u16 {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_u16(*self);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        reader.read_u16()
+    }
+}
+
+impl StateValue for i16 {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_i16(*self);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        reader.read_i16()
+    }
+}
+
+impl StateValue for u32 {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_u32(*self);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        reader.read_u32()
+    }
+}
+
+impl StateValue for i32 {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_i32(*self);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        reader.read_i32()
+    }
+}
+
+impl StateValue for u64 {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_u64(*self);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        reader.read_u64()
+    }
+}
+
+/// Always stored as 64 bits, so states can be shared between platforms.
+impl StateValue for usize {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_u64(*self as u64);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        usize::try_from(reader.read_u64()?).map_err(|_| INVALID)
+    }
+}
+
+impl<T: StateValue> StateValue for Option<T> {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.is_some());
+        if let Some(value) = self {
+            value.write(writer);
+        }
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        if reader.read_bool()? { Ok(Some(T::read(reader)?)) } else { Ok(None) }
+    }
+}
+
+impl<T: StateValue + Default, const N: usize> StateValue for [T; N] {
+    fn write(&self, writer: &mut StateWriter) {
+        for item in self {
+            item.write(writer);
+        }
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        let mut array: [T; N] = core::array::from_fn(|_| T::default());
+        for item in &mut array {
+            *item = T::read(reader)?;
+        }
+        Ok(array)
+    }
+}
+
+impl<T: StateValue> StateValue for Vec2<T> {
+    fn write(&self, writer: &mut StateWriter) {
+        self.x.write(writer);
+        self.y.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self { x: T::read(reader)?, y: T::read(reader)? })
+    }
+}
+
+/// Fieldless enums, stored as their discriminant.
+macro_rules! enum_value {
+    ($name:ident { $($variant:ident),+ }) => {
+        impl StateValue for $name {
+            fn write(&self, writer: &mut StateWriter) {
+                writer.write_u8(*self as u8);
+            }
+
+            fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+                let value = reader.read_u8()?;
+                [$($name::$variant),+].into_iter().find(|v| *v as u8 == value).ok_or(INVALID)
+            }
+        }
+    };
+}
+
+enum_value!(TileMode { Colors4, Colors16 });
+enum_value!(SpriteSize { Normal, Tall, Zoom });
+enum_value!(BlendMode { Normal, Add, Subtract, Average });
+enum_value!(PlanePriority { BehindMain, AboveMain, AboveSprites });
+enum_value!(WindowMask { Off, Inside, Outside });
+enum_value!(CycleMode { Loop, PingPong, OneShot });
+
+impl StateValue for RGBA12 {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_u16(self.data);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self { data: reader.read_u16()? })
+    }
+}
+
+impl StateValue for TileID {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.0);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self(reader.read_u8()?))
+    }
+}
+
+impl StateValue for Cell {
+    fn write(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.id.0);
+        writer.write_u8(self.flags.0);
+        writer.write_u16(self.colors.0);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Cell::new(reader.read_u8()?, reader.read_u8()?, reader.read_u16()?))
+    }
+}
+
+impl<const BITS_PER_PIXEL: usize> StateValue for Tile<BITS_PER_PIXEL> {
+    fn write(&self, writer: &mut StateWriter) {
+        for cluster in &self.clusters {
+            writer.write_bytes(&cluster.data);
+        }
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        let mut tile = Self::default();
+        for cluster in &mut tile.clusters {
+            cluster.data.copy_from_slice(reader.read_bytes(BITS_PER_PIXEL)?);
+        }
+        Ok(tile)
+    }
+}
+
+impl StateValue for TileAnim {
+    fn write(&self, writer: &mut StateWriter) {
+        self.tile.write(writer);
+        self.fps.write(writer);
+        self.len.write(writer);
+        self.frames.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        let tile = TileID::read(reader)?;
+        let fps = u8::read(reader)?;
+        let len = u8::read(reader)?;
+        if len == 0 || len as usize > TILE_ANIM_FRAMES {
+            return Err(INVALID);
+        }
+        let frames = <[TileID; TILE_ANIM_FRAMES]>::read(reader)?;
+        Ok(Self { tile, fps, len, frames })
+    }
+}
+
+impl StateValue for BankCheckpoint {
+    fn write(&self, writer: &mut StateWriter) {
+        self.tiles.write(writer);
+        self.colors.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self { tiles: u16::read(reader)?, colors: u8::read(reader)? })
+    }
+}
+
+impl StateValue for ColorCycle {
+    fn write(&self, writer: &mut StateWriter) {
+        self.start.write(writer);
+        self.end.write(writer);
+        self.fps.write(writer);
+        self.mode.write(writer);
+        self.reverse.write(writer);
+        self.paused.write(writer);
+        self.elapsed.write(writer);
+        self.offset.write(writer);
+        self.last_frame.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        let start = u8::read(reader)?;
+        let end = u8::read(reader)?;
+        if start >= end || end >= COLORS_PER_PALETTE {
+            return Err(INVALID);
+        }
+        let mut cycle = ColorCycle::new(start, end, u8::read(reader)?);
+        cycle.mode = CycleMode::read(reader)?;
+        cycle.reverse = bool::read(reader)?;
+        cycle.paused = bool::read(reader)?;
+        cycle.elapsed = usize::read(reader)?;
+        cycle.offset = u8::read(reader)?;
+        cycle.last_frame = Option::read(reader)?;
+        Ok(cycle)
+    }
+}
+
+impl StateValue for SpriteEntry {
+    fn write(&self, writer: &mut StateWriter) {
+        self.x.write(writer);
+        self.y.write(writer);
+        writer.write_u8(self.id.0);
+        writer.write_u8(self.flags.0);
+        writer.write_u16(self.colors.0);
+        self.size.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self {
+            x: i16::read(reader)?,
+            y: i16::read(reader)?,
+            id: TileID(reader.read_u8()?),
+            flags: TileFlags(reader.read_u8()?),
+            colors: Palette(reader.read_u16()?),
+            size: SpriteSize::read(reader)?,
+        })
+    }
+}
+
+impl StateValue for SpriteCollisions {
+    fn write(&self, writer: &mut StateWriter) {
+        self.sprites.write(writer);
+        self.bg.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self {
+            sprites: StateValue::read(reader)?,
+            bg: StateValue::read(reader)?,
+        })
+    }
+}
+
+impl StateValue for Affine {
+    fn write(&self, writer: &mut StateWriter) {
+        self.a.write(writer);
+        self.b.write(writer);
+        self.c.write(writer);
+        self.d.write(writer);
+        self.origin.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self {
+            a: i16::read(reader)?,
+            b: i16::read(reader)?,
+            c: i16::read(reader)?,
+            d: i16::read(reader)?,
+            origin: Vec2::read(reader)?,
+        })
+    }
+}
+
+impl StateValue for ColorMath {
+    fn write(&self, writer: &mut StateWriter) {
+        self.brightness.write(writer);
+        self.sprite_blend.write(writer);
+        self.bg_blend.write(writer);
+        self.shadow_color.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self {
+            brightness: i16::read(reader)?,
+            sprite_blend: BlendMode::read(reader)?,
+            bg_blend: BlendMode::read(reader)?,
+            shadow_color: Option::read(reader)?,
+        })
+    }
+}
+
+impl StateValue for Mosaic {
+    fn write(&self, writer: &mut StateWriter) {
+        self.bg.write(writer);
+        self.sprites.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self { bg: u8::read(reader)?, sprites: u8::read(reader)? })
+    }
+}
+
+impl StateValue for IrqTrigger {
+    fn write(&self, writer: &mut StateWriter) {
+        match *self {
+            IrqTrigger::Off => writer.write_u8(0),
+            IrqTrigger::Positions { x, len } => {
+                writer.write_u8(1);
+                x.write(writer);
+                writer.write_u8(len);
+            },
+            IrqTrigger::Every(n) => {
+                writer.write_u8(2);
+                writer.write_u16(n);
+            },
+            IrqTrigger::TileColumns => writer.write_u8(3),
+        }
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        match reader.read_u8()? {
+            0 => Ok(IrqTrigger::Off),
+            1 => {
+                let x = <[u16; IRQ_X_COUNT]>::read(reader)?;
+                let len = reader.read_u8()?;
+                if len as usize > IRQ_X_COUNT {
+                    return Err(INVALID);
+                }
+                Ok(IrqTrigger::Positions { x, len })
+            },
+            2 => Ok(IrqTrigger::Every(reader.read_u16()?)),
+            3 => Ok(IrqTrigger::TileColumns),
+            _ => Err(INVALID),
+        }
+    }
+}
+
+impl StateValue for RasterSplit {
+    fn write(&self, writer: &mut StateWriter) {
+        self.line.write(writer);
+        self.bg_map_bank.write(writer);
+        self.bg_tile_bank.write(writer);
+        self.scroll.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self {
+            line: u16::read(reader)?,
+            bg_map_bank: u8::read(reader)?,
+            bg_tile_bank: u8::read(reader)?,
+            scroll: Option::read(reader)?,
+        })
+    }
+}
+
+impl StateValue for RasterEffects {
+    fn write(&self, writer: &mut StateWriter) {
+        self.scroll.enabled.write(writer);
+        self.scroll.lines.write(writer);
+        self.bg_colors.enabled.write(writer);
+        self.bg_colors.lines.write(writer);
+        self.splits.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        let mut raster = RasterEffects::new();
+        raster.scroll.enabled = bool::read(reader)?;
+        raster.scroll.lines = StateValue::read(reader)?;
+        raster.bg_colors.enabled = bool::read(reader)?;
+        raster.bg_colors.lines = StateValue::read(reader)?;
+        raster.splits = StateValue::read(reader)?;
+        Ok(raster)
+    }
+}
+
+impl StateValue for BgPlane {
+    fn write(&self, writer: &mut StateWriter) {
+        self.enabled.write(writer);
+        self.map_bank.write(writer);
+        self.tile_bank.write(writer);
+        self.scroll.write(writer);
+        self.wrap.write(writer);
+        self.priority.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self {
+            enabled: bool::read(reader)?,
+            map_bank: u8::read(reader)?,
+            tile_bank: u8::read(reader)?,
+            scroll: Vec2::read(reader)?,
+            wrap: bool::read(reader)?,
+            priority: PlanePriority::read(reader)?,
+        })
+    }
+}
+
+impl StateValue for WindowSpan {
+    fn write(&self, writer: &mut StateWriter) {
+        self.left.write(writer);
+        self.right.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self { left: u16::read(reader)?, right: u16::read(reader)? })
+    }
+}
+
+impl StateValue for Window {
+    fn write(&self, writer: &mut StateWriter) {
+        self.enabled.write(writer);
+        self.bg.write(writer);
+        self.sprites.write(writer);
+        self.color_math.write(writer);
+        self.lines.write(writer);
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, &'static str> {
+        Ok(Self {
+            enabled: bool::read(reader)?,
+            bg: WindowMask::read(reader)?,
+            sprites: WindowMask::read(reader)?,
+            color_math: WindowMask::read(reader)?,
+            lines: StateValue::read(reader)?,
+        })
+    }
+}
@@ -2,6 +2,38 @@ use core::array::from_fn;
 
 use crate::*;
 
+/// Determines how many pixels a single sprite entry covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SpriteSize {
+    /// A single 8x8 tile.
+    #[default]
+    Normal,
+    /// 8x16, the tile is drawn on top of the next tile ID. Flipping vertically also swaps
+    /// both tiles, and rotation is ignored. The last tile in a bank can't be the top half:
+    /// those sprites are rejected on insert (ID 255) or not drawn (ID 127 in 16 color banks).
+    Tall,
+    /// 16x16, a single tile magnified 2x.
+    Zoom,
+}
+
+impl SpriteSize {
+    /// Width in pixels.
+    pub const fn width(self) -> i16 {
+        match self {
+            SpriteSize::Normal | SpriteSize::Tall => TILE_SIZE as i16,
+            SpriteSize::Zoom => TILE_SIZE as i16 * 2,
+        }
+    }
+
+    /// Height in pixels.
+    pub const fn height(self) -> i16 {
+        match self {
+            SpriteSize::Normal => TILE_SIZE as i16,
+            SpriteSize::Tall | SpriteSize::Zoom => TILE_SIZE as i16 * 2,
+        }
+    }
+}
+
 /// A sprite represents a tile's position on the screen and the palette
 /// used to draw it.
 #[derive(Debug, Clone, Default)]
@@ -11,6 +43,35 @@ pub struct SpriteEntry {
     pub id: TileID,
     pub flags: TileFlags,
     pub colors: Palette,
+    pub size: SpriteSize,
+}
+
+impl SpriteEntry {
+    /// Tile and tile coordinates used by the sprite pixel at "x" and "y",
+    /// relative to the sprite's top-left corner.
+    #[inline]
+    pub fn tile_coords(&self, x: u8, y: u8) -> (TileID, u8, u8) {
+        match self.size {
+            SpriteSize::Normal => {
+                let (tx, ty) = self.flags.transform_coords(x, y, TILE_SIZE);
+                (self.id, tx, ty)
+            },
+            SpriteSize::Zoom => {
+                let (tx, ty) = self.flags.transform_coords(x / 2, y / 2, TILE_SIZE);
+                (self.id, tx, ty)
+            },
+            SpriteSize::Tall => {
+                let flags = self.flags.with_rotation(false);
+                let mut bottom = y >= TILE_SIZE;
+                if flags.is_flipped_y() {
+                    bottom = !bottom;
+                }
+                let id = if bottom { TileID(self.id.0.wrapping_add(1)) } else { self.id };
+                let (tx, ty) = flags.transform_coords(x, y % TILE_SIZE, TILE_SIZE);
+                (id, tx, ty)
+            },
+        }
+    }
 }
 
 /// Holds a "presence" mask that helps the iterator figure out if any given slot
@@ -23,6 +84,19 @@ pub struct Scanline {
     pub mask: u16,
     // Tracks which sprites are visible in this line
     pub sprites: [u8; SPRITES_PER_LINE],
+    // How many sprites didn't fit in this line
+    pub dropped: u8,
+}
+
+/// Sprite statistics for the current frame, useful to diagnose "vanishing" sprites.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpriteStats {
+    /// Sprites inserted this frame.
+    pub sprite_count: u16,
+    /// Sprites rejected entirely because the sprite table was full.
+    pub dropped_sprites: u16,
+    /// Scanlines that had more than SPRITES_PER_LINE sprites.
+    pub overflowed_lines: u16,
 }
 
 /// Manages sprites inserted in a single frame
@@ -30,7 +104,11 @@ pub struct Scanline {
 pub struct SpriteGenerator {
     pub sprites: [SpriteEntry; MAX_SPRITES],
     pub scanlines: [Scanline; MAX_RESOLUTION_Y],
-    sprite_count: u8,
+    pub(crate) sprite_count: u8,
+    pub(crate) dropped_sprites: u16,
+    pub(crate) overflowed_lines: u16,
+    // Sprite index with the highest priority when a scanline overflows
+    pub(crate) flicker_offset: u8,
 }
 
 impl SpriteGenerator {
@@ -41,16 +119,33 @@ impl SpriteGenerator {
                 mask: 0,
                 sprite_count: 0,
                 sprites: Default::default(),
+                dropped: 0,
             }),
             sprite_count: 0,
+            dropped_sprites: 0,
+            overflowed_lines: 0,
+            flicker_offset: 0,
         }
     }
 
-    pub fn reset(&mut self) {
+    /// Clears all sprites. If "flicker" is true, the sprite priority used when a scanline
+    /// overflows is rotated based on the previous frame's sprite count, so that dropped
+    /// sprites alternate between frames instead of disappearing permanently.
+    pub fn reset(&mut self, flicker: bool) {
+        if flicker && self.sprite_count > 0 {
+            let step = (SPRITES_PER_LINE / 2) as u16;
+            let offset = (self.flicker_offset as u16 + step) % self.sprite_count as u16;
+            self.flicker_offset = offset as u8;
+        } else {
+            self.flicker_offset = 0;
+        }
         self.sprite_count = 0;
+        self.dropped_sprites = 0;
+        self.overflowed_lines = 0;
         for line in &mut self.scanlines {
             line.mask = 0;
             line.sprite_count = 0;
+            line.dropped = 0;
             // Resetting actual pixels does not seem necessary. Restore if garbage is visible
             // for sprite in &mut line.sprites {
             //     sprite.pixels = Cluster::default();
@@ -58,38 +153,38 @@ impl SpriteGenerator {
         }
     }
 
+    /// Adds a sprite to the sprite table and the scanlines it covers. "sprite" is in screen
+    /// coordinates. Returns None if the sprite isn't visible, the table is full, or a Tall
+    /// sprite uses ID 255 (its bottom tile would be out of range).
     pub fn insert(
         &mut self,
-        x: i16,
-        y: i16,
+        sprite: SpriteEntry,
         screen_width: u16,
         screen_height: u16,
-        flags: TileFlags,
-        id: TileID,
-        colors: Palette,
-    ) {
-        let w = TILE_SIZE as i16;
-        let h = TILE_SIZE as i16;
+    ) -> Option<u8> {
+        let (x, y) = (sprite.x, sprite.y);
+        let w = sprite.size.width();
+        let h = sprite.size.height();
 
         if self.sprite_count == u8::MAX {
-            return;
+            self.dropped_sprites = self.dropped_sprites.saturating_add(1);
+            return None;
         }
 
         if x >= screen_width as i16 || y >= screen_height as i16 {
-            return;
+            return None;
         }
 
         if x <= -w || y <= -h {
-            return;
+            return None;
+        }
+
+        if sprite.size == SpriteSize::Tall && sprite.id.0 == u8::MAX {
+            return None;
         }
 
         // Write sprite to sprite bank
-        let sprite = &mut self.sprites[self.sprite_count as usize];
-        sprite.x = x;
-        sprite.y = y;
-        sprite.id = id;
-        sprite.flags = flags;
-        sprite.colors = colors;
+        self.sprites[self.sprite_count as usize] = sprite;
 
         // Write sprite index and mask info to scanline
         let min_x = (-x).max(0);
@@ -102,7 +197,26 @@ impl SpriteGenerator {
             // Acquire scanline ref
             let line = &mut self.scanlines[screen_y as usize];
             if line.sprite_count as usize >= SPRITES_PER_LINE {
-                return;
+                if line.dropped == 0 {
+                    self.overflowed_lines += 1;
+                }
+                line.dropped = line.dropped.saturating_add(1);
+                // Lowest priority sprite in this line gets evicted, unless it's the new one.
+                // Without flicker priority is simply the insertion order, and the new
+                // sprite is always the one dropped.
+                let rank = |index: u8| index.wrapping_sub(self.flicker_offset);
+                let mut evicted = 0;
+                for i in 1..SPRITES_PER_LINE {
+                    if rank(line.sprites[i]) > rank(line.sprites[evicted]) {
+                        evicted = i;
+                    }
+                }
+                if rank(line.sprites[evicted]) < rank(self.sprite_count) {
+                    continue;
+                }
+                // Preserves drawing order, new sprite goes last
+                line.sprites.copy_within(evicted + 1.., evicted);
+                line.sprite_count -= 1;
             }
             let local_sprite = line.sprite_count as usize;
             line.sprites[local_sprite] = self.sprite_count;
@@ -120,6 +234,16 @@ impl SpriteGenerator {
                 line.mask |= 1 << slot;
             }
         }
+        let index = self.sprite_count;
         self.sprite_count += 1;
+        Some(index)
+    }
+
+    pub fn stats(&self) -> SpriteStats {
+        SpriteStats {
+            sprite_count: self.sprite_count as u16,
+            dropped_sprites: self.dropped_sprites,
+            overflowed_lines: self.overflowed_lines,
+        }
     }
 }
@@ -0,0 +1,149 @@
+use super::snapshot::*;
+use super::*;
+use std::{collections::VecDeque, vec, vec::Vec};
+
+/// Reference 4-way flood fill, on a plain pixel array.
+fn reference_fill(pixels: &mut [u8], w: usize, x: usize, y: usize, value: u8) {
+    let target = pixels[y * w + x];
+    let h = pixels.len() / w;
+    let mut queue = VecDeque::from([(x, y)]);
+    while let Some((x, y)) = queue.pop_front() {
+        if pixels[y * w + x] != target {
+            continue;
+        }
+        pixels[y * w + x] = value;
+        if x > 0 {
+            queue.push_back((x - 1, y));
+        }
+        if x + 1 < w {
+            queue.push_back((x + 1, y));
+        }
+        if y > 0 {
+            queue.push_back((x, y - 1));
+        }
+        if y + 1 < h {
+            queue.push_back((x, y + 1));
+        }
+    }
+}
+
+fn bitmap_pixels(bitmap: &Bitmap, bank: &Bank) -> Vec<u8> {
+    let mut result = vec![];
+    for y in 0..bitmap.height() {
+        for x in 0..bitmap.width() {
+            result.push(bitmap.get_pixel(bank, x, y).unwrap());
+        }
+    }
+    result
+}
+
+#[test]
+fn test_bitmap_primitives() {
+    let mut bank = test_bank();
+    let bitmap = Bitmap::new(&mut bank, 2, 2).unwrap();
+    assert_eq!(bitmap.first_tile, TileID(4));
+    assert_eq!(bank.tiles.count(), 8);
+    assert_eq!((bitmap.width(), bitmap.height()), (16, 16));
+
+    // Pixels are laid out across tiles in row order
+    bitmap.set_pixel(&mut bank, 9, 1, 3);
+    bitmap.set_pixel(&mut bank, 2, 12, 2);
+    bitmap.set_pixel(&mut bank, -1, 20, 1); // Ignored
+    assert_eq!(bank.tiles.get_pixel(TileID(5), 1, 1), 3);
+    assert_eq!(bank.tiles.get_pixel(TileID(6), 2, 4), 2);
+    assert_eq!(bitmap.get_pixel(&bank, 16, 0), None);
+
+    bitmap.clear(&mut bank, 0);
+    bitmap.rect(&mut bank, 2, 2, 10, 8, 1);
+    let count =
+        |bank: &Bank, value| bitmap_pixels(&bitmap, bank).iter().filter(|p| **p == value).count();
+    assert_eq!(count(&bank, 1), 10 * 2 + 6 * 2);
+    // Fill stays inside the outline
+    bitmap.flood_fill(&mut bank, 5, 5, 2);
+    assert_eq!(count(&bank, 2), 8 * 6);
+    bitmap.flood_fill(&mut bank, 0, 0, 3);
+    assert_eq!(count(&bank, 3), 256 - 10 * 8);
+
+    bitmap.clear(&mut bank, 0);
+    bitmap.line(&mut bank, 0, 0, 15, 15, 1);
+    bitmap.line(&mut bank, 15, 0, -5, 0, 1);
+    assert_eq!(count(&bank, 1), 16 + 15);
+    bitmap.clear(&mut bank, 0);
+    bitmap.fill_circle(&mut bank, 8, 8, 3, 2);
+    bitmap.circle(&mut bank, 8, 8, 3, 1);
+    assert_eq!(bitmap.get_pixel(&bank, 8, 8), Some(2));
+    assert_eq!(bitmap.get_pixel(&bank, 11, 8), Some(1));
+    assert_eq!(bitmap.get_pixel(&bank, 12, 8), Some(0));
+
+    // Shapes that extend past the i16 range are clipped instead of overflowing
+    bitmap.clear(&mut bank, 0);
+    bitmap.rect(&mut bank, 0, 0, i16::MAX, i16::MAX, 1);
+    bitmap.rect(&mut bank, i16::MAX, i16::MAX, i16::MAX, 1, 1);
+    assert_eq!(count(&bank, 1), 16 + 15);
+    bitmap.circle(&mut bank, i16::MIN, i16::MAX, i16::MAX, 2);
+    assert_eq!(count(&bank, 2), 0);
+    bitmap.fill_circle(&mut bank, i16::MIN + 20, 8, i16::MAX, 3);
+    assert_eq!(count(&bank, 3), 256);
+
+    // Blit skips transparent pixels and respects flags
+    bitmap.clear(&mut bank, 2);
+    bitmap.blit(&mut bank, TILE_ARROW, 8, 0, TileFlags::default().with_flip_x(true));
+    assert_eq!(bitmap.get_pixel(&bank, 15, 0), Some(1));
+    assert_eq!(bitmap.get_pixel(&bank, 8, 0), Some(2));
+}
+
+#[test]
+fn test_bitmap_flood_fill_complex() {
+    // Enough small spans to overflow the fill's seed stack
+    let mut bank = Bank::new();
+    let bitmap = Bitmap::new(&mut bank, 16, 16).unwrap();
+    let mut seed: u32 = 12345;
+    for y in 0..bitmap.height() {
+        for x in 0..bitmap.width() {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let wall = (seed >> 16) % 100 < 35 || (x % 4 == 2 && y % 16 != 0);
+            bitmap.set_pixel(&mut bank, x, y, wall as u8);
+        }
+    }
+    bitmap.set_pixel(&mut bank, 0, 0, 0);
+    let mut expected = bitmap_pixels(&bitmap, &bank);
+    reference_fill(&mut expected, 128, 0, 0, 3);
+
+    bitmap.flood_fill(&mut bank, 0, 0, 3);
+    assert!(bitmap_pixels(&bitmap, &bank) == expected);
+}
+
+#[test]
+fn test_bitmap_16_colors() {
+    let mut bank = Bank::new_16_colors();
+    let bitmap = Bitmap::new(&mut bank, 1, 1).unwrap();
+    for x in 0..8 {
+        bitmap.line(&mut bank, x, 0, x, 7, x as u8 * 2);
+    }
+    bitmap.flood_fill(&mut bank, 3, 3, 15);
+    assert_eq!(bitmap.get_pixel(&bank, 3, 7), Some(15));
+    assert_eq!(bitmap.get_pixel(&bank, 4, 7), Some(8));
+    assert_eq!(bank.tiles.get_16(TileID(0)).get_pixel(7, 5), 14);
+}
+
+#[test]
+fn bitmap_drawing() {
+    let video = new_video();
+    let mut bank = test_bank();
+    let mut map = Tilemap::<100>::new(10, 8);
+    let bitmap = Bitmap::new(&mut bank, 7, 5).unwrap();
+    bitmap.place(&mut map, 0, 0, Palette::new(0, 15, 8, 13));
+
+    bitmap.rect(&mut bank, 0, 0, bitmap.width(), bitmap.height(), 1);
+    bitmap.circle(&mut bank, 16, 16, 11, 3);
+    bitmap.fill_circle(&mut bank, 16, 16, 6, 2);
+    bitmap.flood_fill(&mut bank, 16, 5, 1);
+    for i in 0..6 {
+        bitmap.line(&mut bank, 32, 4 + i * 6, 52, 34 - i * 4, (i % 3) as u8 + 1);
+    }
+    bitmap.blit(&mut bank, TILE_ARROW, 44, 2, TileFlags::default());
+    bitmap.blit(&mut bank, TILE_ARROW, 4, 28, TileFlags::default().with_rotation(true));
+
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("bitmap_drawing", &video, &frame);
+}
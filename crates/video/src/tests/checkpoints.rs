@@ -0,0 +1,98 @@
+use super::snapshot::*;
+use super::*;
+
+#[test]
+fn test_bank_checkpoints() {
+    let mut bank = Bank::new();
+    bank.colors.push_color(RGBA12::BLACK);
+    bank.append_tile(&Tile::default()).unwrap();
+    let base_version = bank.tiles.version();
+
+    // Scene A
+    bank.push_checkpoint().unwrap();
+    bank.colors.push_color(RGBA12::RED);
+    bank.colors.push_color(RGBA12::GREEN);
+    bank.append_tiles(&[tile_from_rows(["11111111"; 8]); 3]).unwrap();
+    bank.tiles.set_anim(TileAnim::new(TileID(1), 10, &[TileID(1), TileID(2)]));
+    let cycle = bank.colors.add_cycle(ColorCycle::new(1, 2, 10)).unwrap();
+
+    // Nested scene B
+    bank.push_checkpoint().unwrap();
+    bank.append_tile(&tile_from_rows(["22222222"; 8])).unwrap();
+    assert_eq!(bank.checkpoint_depth(), 2);
+
+    let popped = bank.pop_checkpoint().unwrap();
+    assert_eq!(popped, BankCheckpoint { tiles: 4, colors: 3 });
+    assert_eq!(bank.tiles.count(), 4);
+    assert_eq!(bank.tiles.anims().len(), 1);
+    assert!(bank.colors.cycle(cycle).is_some());
+
+    bank.pop_checkpoint().unwrap();
+    assert_eq!(bank.tiles.count(), 1);
+    assert_eq!(bank.colors.color_count(), 1);
+    assert!(bank.tiles.anims().is_empty());
+    assert_eq!(bank.tiles.resolve_anim(TileID(1), 10, 60), TileID(1));
+    assert!(bank.colors.cycle(cycle).is_none());
+    assert_ne!(bank.tiles.version(), base_version);
+
+    assert_eq!(bank.pop_checkpoint(), Err("No checkpoint to pop"));
+    for _ in 0..CHECKPOINT_COUNT {
+        bank.push_checkpoint().unwrap();
+    }
+    assert_eq!(bank.push_checkpoint(), Err("Checkpoint stack overflow"));
+}
+
+#[test]
+fn test_bank_version() {
+    let mut bank = test_bank();
+    let version = bank.tiles.version();
+    bank.append_tile(&Tile::default()).unwrap();
+    assert_ne!(bank.tiles.version(), version);
+
+    // Popping and re-adding the same number of tiles still changes the version
+    bank.push_checkpoint().unwrap();
+    bank.append_tile(&tile_from_rows(["33333333"; 8])).unwrap();
+    bank.pop_checkpoint().unwrap();
+    let version = bank.tiles.version();
+    bank.append_tile(&tile_from_rows(["11111111"; 8])).unwrap();
+    assert_ne!(bank.tiles.version(), version);
+}
+
+#[test]
+fn test_checkpoint_anims_after_removal() {
+    let mut bank = test_bank();
+    let anim_a = TileAnim::new(TILE_ARROW, 10, &[TILE_ARROW, TILE_CHECKER]);
+    let anim_c = TileAnim::new(TILE_BLOCK, 10, &[TILE_BLOCK, TILE_ARROW]);
+    bank.tiles.set_anim(anim_a);
+    bank.tiles.set_anim(anim_c);
+
+    // A scene adds animation B, and removes A (which moves B into A's slot)
+    bank.push_checkpoint().unwrap();
+    let tile = bank.append_tile(&tile_from_rows(["12121212"; 8])).unwrap();
+    bank.tiles.set_anim(TileAnim::new(tile, 10, &[tile, TILE_CHECKER]));
+    bank.tiles.remove_anim(TILE_ARROW);
+    // ...and replaces C with an animation that uses the new tile
+    bank.tiles.set_anim(TileAnim::new(TILE_BLOCK, 10, &[TILE_BLOCK, tile]));
+
+    // B and the replaced C are gone, and nothing resolves to the discarded tile
+    bank.pop_checkpoint().unwrap();
+    assert!(bank.tiles.anims().is_empty());
+    for id in [TILE_ARROW, TILE_BLOCK, tile] {
+        for frame in 0..4 {
+            assert_eq!(bank.tiles.resolve_anim(id, frame * 6, 60), id);
+        }
+    }
+
+    // Animations that only use kept tiles survive, even if they were reordered
+    bank.push_checkpoint().unwrap();
+    bank.tiles.set_anim(anim_a);
+    bank.tiles.set_anim(anim_c);
+    bank.push_checkpoint().unwrap();
+    let tile = bank.append_tile(&tile_from_rows(["12121212"; 8])).unwrap();
+    bank.tiles.set_anim(TileAnim::new(tile, 10, &[tile, TILE_CHECKER]));
+    bank.tiles.remove_anim(TILE_ARROW);
+    bank.pop_checkpoint().unwrap();
+    assert_eq!(bank.tiles.anims(), &[anim_c]);
+    assert_eq!(bank.tiles.resolve_anim(TILE_BLOCK, 6, 60), TILE_ARROW);
+    assert_eq!(bank.tiles.resolve_anim(tile, 6, 60), tile);
+}
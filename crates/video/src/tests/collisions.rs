@@ -0,0 +1,102 @@
+use super::snapshot::*;
+use super::*;
+use core::sync::atomic::{AtomicU32, Ordering};
+use std::vec;
+
+fn draw_block(video: &mut VideoChip, x: i16, y: i16) -> u8 {
+    video
+        .draw_fg_tile(DrawBundle {
+            x,
+            y,
+            id: TILE_BLOCK,
+            flags: TileFlags::default(),
+            colors: Palette::default(),
+            size: SpriteSize::Normal,
+        })
+        .unwrap()
+}
+
+fn empty_map_with_block(col: i16, row: i16) -> Tilemap<100> {
+    let mut map = Tilemap::<100>::new(10, 8);
+    map.set_cell(
+        col,
+        row,
+        Cell {
+            id: TILE_BLOCK,
+            flags: TileFlags::default(),
+            colors: Palette::default(),
+        },
+    );
+    map
+}
+
+#[test]
+fn test_collision_registers() {
+    let mut video = new_video();
+    video.detect_collisions = true;
+    let bank = test_bank();
+    let map = empty_map_with_block(4, 4);
+
+    let a = draw_block(&mut video, 2, 2);
+    let b = draw_block(&mut video, 6, 5);
+    let alone = draw_block(&mut video, 50, 2);
+    let over_bg = draw_block(&mut video, 36, 36);
+
+    render(&video, &[&bank], &[&map]);
+    let collisions = video.collisions();
+    assert!(collisions.any());
+    assert!(collisions.with_sprite(a) && collisions.with_sprite(b));
+    assert!(!collisions.with_sprite(alone) && !collisions.with_sprite(over_bg));
+    assert!(collisions.with_bg(over_bg));
+    assert!(!collisions.with_bg(a) && !collisions.with_bg(b) && !collisions.with_bg(alone));
+
+    // Registers are cleared on the next render
+    video.frame_start(false);
+    draw_block(&mut video, 50, 2);
+    render(&video, &[&bank], &[&map]);
+    assert!(!video.collisions().any());
+}
+
+#[test]
+fn test_collisions_disabled() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = empty_map_with_block(0, 0);
+    draw_block(&mut video, 2, 2);
+    draw_block(&mut video, 4, 4);
+    render(&video, &[&bank], &[&map]);
+    assert!(!video.collisions().any());
+
+    // Registers from the last frame with detection enabled don't linger
+    video.detect_collisions = true;
+    render(&video, &[&bank], &[&map]);
+    assert!(video.collisions().any());
+    video.detect_collisions = false;
+    render(&video, &[&bank], &[&map]);
+    assert!(!video.collisions().any());
+}
+
+static COLLISION_CALLS: AtomicU32 = AtomicU32::new(0);
+
+#[test]
+fn test_collision_irq() {
+    let mut video = new_video();
+    video.detect_collisions = true;
+    video.irq_collision = Some(|_video, collision| {
+        assert_eq!(collision.sprite, 0);
+        assert_eq!(collision.other, Some(1));
+        assert_eq!((collision.x, collision.y), (10, 20));
+        COLLISION_CALLS.fetch_add(1, Ordering::Relaxed);
+    });
+    let bank = test_bank();
+    let map = empty_map_with_block(9, 7);
+    draw_block(&mut video, 10, 20);
+    draw_block(&mut video, 10, 20);
+
+    // Called once per frame, even though every pixel overlaps
+    let mut frame = vec![RGBA32::TRANSPARENT; SCREEN_W as usize * SCREEN_H as usize];
+    video.render_frame(&[&bank], &[&map], &mut frame);
+    assert_eq!(COLLISION_CALLS.load(Ordering::Relaxed), 1);
+    video.render_frame(&[&bank], &[&map], &mut frame);
+    assert_eq!(COLLISION_CALLS.load(Ordering::Relaxed), 2);
+}
@@ -0,0 +1,83 @@
+use super::*;
+
+fn test_colors() -> ColorBank {
+    let mut colors = ColorBank::new();
+    colors.load_default();
+    colors
+}
+
+/// Returns the range of colors after advancing the video chip "frames" times.
+fn colors_after(colors: &mut ColorBank, video: &mut VideoChip, frames: usize) -> [RGBA12; 4] {
+    for _ in 0..frames {
+        video.frame_start(false);
+        colors.update_cycles(video);
+    }
+    colors.palette[4..8].try_into().unwrap()
+}
+
+#[test]
+fn test_color_cycle_loop() {
+    let mut video = VideoChip::new(64, 48, 60);
+    let mut colors = test_colors();
+    let [a, b, c, d] = [4, 5, 6, 7].map(|i| DEFAULT_PALETTE[i]);
+    // One step every 4 frames
+    let id = colors.add_cycle(ColorCycle::new(4, 7, 15)).unwrap();
+    colors.update_cycles(&video);
+
+    assert_eq!(colors_after(&mut colors, &mut video, 3), [a, b, c, d]);
+    assert_eq!(colors_after(&mut colors, &mut video, 1), [d, a, b, c]);
+    assert_eq!(colors_after(&mut colors, &mut video, 12), [a, b, c, d]);
+
+    // Paused cycles keep their colors, and resume where they stopped
+    colors.pause_cycle(id);
+    assert_eq!(colors_after(&mut colors, &mut video, 8), [a, b, c, d]);
+    colors.resume_cycle(id);
+    assert_eq!(colors_after(&mut colors, &mut video, 8), [c, d, a, b]);
+
+    // Frames where the video chip is paused don't advance the cycle
+    video.frame_start(true);
+    colors.update_cycles(&video);
+    assert_eq!(colors_after(&mut colors, &mut video, 0), [c, d, a, b]);
+
+    // Removing restores the original order
+    assert!(colors.remove_cycle(id).is_some());
+    assert_eq!(colors.palette[4..8], [a, b, c, d]);
+    assert!(colors.cycle(id).is_none());
+}
+
+#[test]
+fn test_color_cycle_modes() {
+    let mut video = VideoChip::new(64, 48, 60);
+    let [a, b, c, d] = [4, 5, 6, 7].map(|i| DEFAULT_PALETTE[i]);
+
+    let mut colors = test_colors();
+    colors.add_cycle(ColorCycle::new(4, 7, 60).reversed()).unwrap();
+    colors.update_cycles(&video);
+    assert_eq!(colors_after(&mut colors, &mut video, 1), [b, c, d, a]);
+
+    let mut colors = test_colors();
+    colors.add_cycle(ColorCycle::new(4, 7, 60).with_mode(CycleMode::PingPong)).unwrap();
+    colors.update_cycles(&video);
+    let steps: std::vec::Vec<_> =
+        (0..6).map(|_| colors_after(&mut colors, &mut video, 1)[0]).collect();
+    assert_eq!(steps, [d, c, b, c, d, a]);
+
+    let mut colors = test_colors();
+    let id = colors.add_cycle(ColorCycle::new(4, 7, 60).with_mode(CycleMode::OneShot)).unwrap();
+    colors.update_cycles(&video);
+    assert!(!colors.cycle(id).unwrap().is_finished());
+    assert_eq!(colors_after(&mut colors, &mut video, 10), [b, c, d, a]);
+    assert!(colors.cycle(id).unwrap().is_finished());
+
+    // Ranges can't overlap, since each cycle restores its own colors when removed
+    let overlap = Err("Color cycle overlaps an existing cycle");
+    assert_eq!(colors.add_cycle(ColorCycle::new(7, 9, 1)), overlap);
+    assert_eq!(colors.add_cycle(ColorCycle::new(2, 4, 1)), overlap);
+    assert_eq!(colors.add_cycle(ColorCycle::new(5, 6, 1)), overlap);
+
+    // Capacity
+    for start in [0, 2, 8] {
+        colors.add_cycle(ColorCycle::new(start, start + 1, 1)).unwrap();
+    }
+    assert_eq!(colors.add_cycle(ColorCycle::new(10, 11, 1)), Err("Color cycle capacity exceeded"));
+}
@@ -0,0 +1,69 @@
+use super::snapshot::*;
+use super::*;
+use std::vec::Vec;
+
+#[test]
+fn test_brightness_extremes() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+
+    video.color_math.brightness = -255;
+    let frame = render(&video, &[&bank], &[&map]);
+    assert!(frame.iter().all(|p| p.r == 0 && p.g == 0 && p.b == 0));
+
+    video.color_math.brightness = 255;
+    let frame = render(&video, &[&bank], &[&map]);
+    assert!(frame.iter().all(|p| p.r == 255 && p.g == 255 && p.b == 255));
+}
+
+#[test]
+fn test_inactive_color_math_is_identity() {
+    // Brightness zero and normal blending must match the fast path exactly
+    let mut video = new_video();
+    video.draw_fg_tile(DrawBundle {
+        x: 30,
+        y: 20,
+        id: TILE_BLOCK,
+        flags: TileFlags::default(),
+        colors: Palette::new(0, 1, 6, 8),
+        size: SpriteSize::Normal,
+    });
+    let bank = test_bank();
+    let map = test_tilemap();
+    let fast = render(&video, &[&bank], &[&map]);
+    // Forces the color math path without changing any color
+    video.color_math.shadow_color = Some(15);
+    let slow = render(&video, &[&bank], &[&map]);
+    let iterated = video.iter_pixels(&[&bank], &[&map]).collect::<Vec<_>>();
+    assert_eq!(fast, slow);
+    assert_eq!(fast, iterated);
+}
+
+#[test]
+fn test_color_math() {
+    let mut video = new_video();
+    video.color_math.bg_blend = BlendMode::Average;
+    video.color_math.sprite_blend = BlendMode::Add;
+    video.color_math.shadow_color = Some(1);
+    // Fades out line by line
+    video.irq_line = Some(|iter, _video, _map| {
+        iter.color_math.brightness = -(iter.y() as i16 * 4);
+    });
+
+    // Color 1 (black) is the shadow color, the rest are added to the BG
+    for i in 0..4i16 {
+        video.draw_fg_tile(DrawBundle {
+            x: 4 + (i * 14),
+            y: 4 + (i * 8),
+            id: TILE_BLOCK,
+            flags: TileFlags::default(),
+            colors: Palette::new(0, 1, 12, 1),
+            size: SpriteSize::Normal,
+        });
+    }
+    let bank = test_bank();
+    let map = test_tilemap();
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("color_math", &video, &frame);
+}
@@ -0,0 +1,102 @@
+use super::snapshot::*;
+use super::*;
+
+#[test]
+fn test_irq_trigger_positions() {
+    assert_eq!(IrqTrigger::Off.next(0, 0), None);
+    assert_eq!(IrqTrigger::Every(0).next(0, 0), None);
+    assert_eq!(IrqTrigger::Every(10).next(0, 0), Some(10));
+    assert_eq!(IrqTrigger::Every(10).next(10, 0), Some(20));
+    assert_eq!(IrqTrigger::Every(10).next(13, 0), Some(20));
+
+    // Positions don't need to be sorted
+    let trigger = IrqTrigger::at(&[40, 12, 30]);
+    assert_eq!(trigger.next(0, 0), Some(12));
+    assert_eq!(trigger.next(12, 0), Some(30));
+    assert_eq!(trigger.next(40, 0), None);
+
+    // Tile columns follow the scroll
+    assert_eq!(IrqTrigger::TileColumns.next(0, 0), Some(8));
+    assert_eq!(IrqTrigger::TileColumns.next(0, 3), Some(5));
+    assert_eq!(IrqTrigger::TileColumns.next(5, 3), Some(13));
+    assert_eq!(IrqTrigger::TileColumns.next(0, -3), Some(3));
+}
+
+#[test]
+fn test_irq_x_split_screen() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    let plain = render(&video, &[&bank], &[&map]);
+    video.scroll = Vec2 { x: 13, y: 7 };
+    let scrolled = render(&video, &[&bank], &[&map]);
+
+    // The right side of the screen scrolls, the left side doesn't
+    video.scroll = Vec2::zero();
+    video.irq_x_trigger = IrqTrigger::at(&[24]);
+    video.irq_x = Some(|iter, _video, _map| {
+        assert_eq!(iter.x(), 24);
+        iter.scroll_x = 13;
+        iter.scroll_y = 7;
+    });
+    video.irq_line = Some(|iter, _video, _map| {
+        iter.scroll_x = 0;
+        iter.scroll_y = 0;
+    });
+    let frame = render(&video, &[&bank], &[&map]);
+    let w = SCREEN_W as usize;
+    for y in 0..SCREEN_H as usize {
+        let line = y * w..(y + 1) * w;
+        assert_eq!(frame[line.clone()][..24], plain[line.clone()][..24]);
+        assert_eq!(frame[line.clone()][24..], scrolled[line][24..]);
+    }
+
+    // The per pixel iterator must match
+    let iterated = video.iter_pixels(&[&bank], &[&map]).collect::<std::vec::Vec<_>>();
+    assert!(frame == iterated);
+
+    // Without a callback the trigger does nothing
+    video.irq_x = None;
+    assert!(plain == render(&video, &[&bank], &[&map]));
+}
+
+#[test]
+fn test_irq_x_snapshot() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    let mut side = Tilemap::<100>::new(10, 8);
+    for row in 0..8 {
+        for col in 0..10 {
+            let colors = Palette::new(0, 3, 11, 3);
+            side.set_cell(col, row, Cell { id: TILE_CHECKER, flags: TileFlags::default(), colors });
+        }
+    }
+    video.scroll = Vec2 { x: 4, y: 0 };
+    video.draw_fg_tile(DrawBundle {
+        x: 30,
+        y: 20,
+        id: TILE_ARROW,
+        flags: TileFlags::default(),
+        colors: Palette::new(0, 1, 6, 8),
+        size: SpriteSize::Zoom,
+    });
+    // Each tile column is offset vertically, and a status bar on the right uses another map
+    video.irq_x_trigger = IrqTrigger::TileColumns;
+    video.irq_x = Some(|iter, _video, _map| {
+        if iter.x() >= 48 {
+            iter.bg_map_bank = 1;
+            iter.scroll_x = 0;
+            iter.scroll_y = 0;
+        } else {
+            iter.scroll_y = iter.x() as i16 / 2;
+        }
+    });
+    video.irq_line = Some(|iter, _video, _map| {
+        iter.bg_map_bank = 0;
+        iter.scroll_x = 4;
+        iter.scroll_y = 0;
+    });
+    let frame = render(&video, &[&bank], &[&map, &side]);
+    assert_snapshot("irq_x", &video, &frame);
+}
@@ -0,0 +1,24 @@
+//! Rendering tests. Scenes are rendered headlessly and compared against the reference
+//! images in "crates/video/snapshots". To (re)generate the references after an intentional
+//! change in the renderer, run the tests with the TATO_UPDATE_SNAPSHOTS environment
+//! variable set, and check the new images before committing them!
+use super::*;
+use tato_math::Vec2;
+
+mod bitmap;
+mod checkpoints;
+mod collisions;
+mod color_cycle;
+mod color_math;
+mod irq;
+mod mosaic;
+mod objects;
+mod overlay;
+mod raster;
+mod render;
+mod save_state;
+mod snapshot;
+mod sprites;
+mod streaming;
+mod tiles;
+mod windows;
@@ -0,0 +1,95 @@
+use super::snapshot::*;
+use super::*;
+
+fn draw_block(video: &mut VideoChip, x: i16, y: i16) {
+    video.draw_fg_tile(DrawBundle {
+        x,
+        y,
+        id: TILE_ARROW,
+        flags: TileFlags::default(),
+        colors: Palette::new(0, 8, 7, 3),
+        size: SpriteSize::Zoom,
+    });
+}
+
+#[test]
+fn test_mosaic_off() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    video.scroll = Vec2 { x: 3, y: 5 };
+    draw_block(&mut video, 20, 12);
+    let plain = render(&video, &[&bank], &[&map]);
+
+    // Sizes 0 and 1 both leave the layers untouched
+    video.mosaic = Mosaic::new(0, 1);
+    assert!(!video.mosaic.is_active());
+    assert!(plain == render(&video, &[&bank], &[&map]));
+}
+
+#[test]
+fn test_mosaic_blocks() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    video.scroll = Vec2 { x: 3, y: 5 };
+    let plain = render(&video, &[&bank], &[&map]);
+
+    // Every pixel takes the color of its block's top-left pixel
+    video.mosaic = Mosaic::new(4, 1);
+    let frame = render(&video, &[&bank], &[&map]);
+    let w = SCREEN_W as usize;
+    for y in 0..SCREEN_H as usize {
+        // The last column is outside the default viewport
+        for x in 0..w - 1 {
+            assert_eq!(frame[y * w + x], plain[(y - y % 4) * w + (x - x % 4)]);
+        }
+    }
+
+    // Sizes are clamped
+    video.mosaic = Mosaic::all(200);
+    let clamped = render(&video, &[&bank], &[&map]);
+    video.mosaic = Mosaic::all(MOSAIC_MAX_SIZE);
+    assert!(clamped == render(&video, &[&bank], &[&map]));
+}
+
+#[test]
+fn test_mosaic_per_layer() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = Tilemap::<100>::new(10, 8);
+    draw_block(&mut video, 21, 13);
+    let plain = render(&video, &[&bank], &[&map]);
+
+    // The BG is a single color, so BG mosaic alone changes nothing
+    video.mosaic = Mosaic::new(8, 1);
+    assert!(plain == render(&video, &[&bank], &[&map]));
+
+    // Sprite blocks are aligned to the screen, not to the sprite
+    video.mosaic = Mosaic::new(1, 8);
+    let frame = render(&video, &[&bank], &[&map]);
+    assert!(plain != frame);
+    let w = SCREEN_W as usize;
+    for y in 0..SCREEN_H as usize {
+        // The last column is outside the default viewport
+        for x in 0..w - 1 {
+            assert_eq!(frame[y * w + x], plain[(y - y % 8) * w + (x - x % 8)]);
+        }
+    }
+}
+
+#[test]
+fn test_mosaic_snapshot() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    draw_block(&mut video, 8, 8);
+    draw_block(&mut video, 36, 20);
+    // Mosaic grows towards the bottom of the screen, on both layers
+    video.irq_line = Some(|iter, _video, _map| {
+        let size = (iter.y() / 12) as u8 * 2;
+        iter.mosaic = Mosaic::new(size, size);
+    });
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("mosaic", &video, &frame);
+}
@@ -0,0 +1,71 @@
+use super::snapshot::*;
+use super::*;
+
+fn pixel(frame: &[RGBA32], x: u16, y: u16) -> RGBA32 {
+    frame[(y * SCREEN_W + x) as usize]
+}
+
+static SPRITE_CELLS: [Cell; 2] = [Cell::new(3, 0, 0x0123), Cell::new(3, 0, 0x0123)];
+
+#[test]
+fn test_object_table() {
+    let mut table = ObjectTable::new();
+    let a = table.add(Object::tile(0, 0, TILE_BLOCK)).unwrap();
+    let b = table.add(Object::tile(8, 0, TILE_BLOCK)).unwrap();
+    assert_eq!(table.count(), 2);
+
+    table.get_mut(a).unwrap().x = 20;
+    assert_eq!(table.get(a).unwrap().x, 20);
+
+    assert!(table.remove(a).is_some());
+    assert!(table.remove(a).is_none());
+    assert!(table.get(a).is_none());
+    assert_eq!(table.iter().map(|(id, _)| id).collect::<std::vec::Vec<_>>(), [b]);
+
+    // Free slots are reused
+    assert_eq!(table.add(Object::tile(0, 0, TILE_EMPTY)), Some(a));
+    while table.add(Object::tile(0, 0, TILE_EMPTY)).is_some() {}
+    assert_eq!(table.count(), OBJECT_COUNT);
+}
+
+#[test]
+fn test_objects_persist() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = Tilemap::<100>::new(10, 8);
+    let red = Palette::new(0, 5, 5, 5);
+    let blue = Palette::new(0, 13, 13, 13);
+
+    let back = video.objects.add(Object {
+        colors: Some(red),
+        priority: 1,
+        ..Object::tile(10, 10, TILE_BLOCK)
+    });
+    video.objects.add(Object { colors: Some(blue), ..Object::tile(14, 10, TILE_BLOCK) }).unwrap();
+    video.objects.add(Object::tilemap(
+        40,
+        30,
+        TilemapRef { cells: &SPRITE_CELLS, columns: 2, rows: 1 },
+    ));
+
+    for _ in 0..3 {
+        video.frame_start(false);
+        video.frame_finish(false);
+        let frame = render(&video, &[&bank], &[&map]);
+        // Higher priority wins, regardless of slot order
+        assert_eq!(pixel(&frame, 15, 12), RGBA32::from(RGBA12::RED));
+        assert_eq!(pixel(&frame, 20, 12), RGBA32::from(RGBA12::BLUE));
+        // Tilemap objects draw all their tiles
+        assert_eq!(pixel(&frame, 40, 30), RGBA32::from(RGBA12::WHITE));
+        assert_eq!(pixel(&frame, 55, 30), RGBA32::from(RGBA12::WHITE));
+        assert_eq!(video.sprite_stats().sprite_count, 4);
+    }
+
+    // Mutations and visibility are picked up on the next frame
+    video.objects.get_mut(back.unwrap()).unwrap().visible = false;
+    video.frame_start(false);
+    video.frame_finish(false);
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_eq!(pixel(&frame, 15, 12), RGBA32::from(RGBA12::BLUE));
+    assert_eq!(video.sprite_stats().sprite_count, 3);
+}
@@ -0,0 +1,136 @@
+use super::snapshot::*;
+use super::*;
+use std::vec;
+use std::vec::Vec;
+
+const HUD_W: u16 = 48;
+const HUD_H: u16 = 16;
+const HUD_KEY: RGBA12 = RGBA12::new(7, 0, 7);
+
+/// A small chip with a single row of tiles and a sprite. The backdrop uses the key color.
+fn hud_video() -> (VideoChip, Tilemap<12>) {
+    let mut hud = VideoChip::new(HUD_W, HUD_H, 60);
+    hud.bg_color = HUD_KEY;
+    hud.crop_color = HUD_KEY;
+    let mut map = Tilemap::<12>::new(6, 2);
+    for col in 0..6 {
+        let colors = Palette::new(0, 3, 11, 3);
+        map.set_cell(col, 0, Cell { id: TILE_CHECKER, flags: TileFlags::default(), colors });
+    }
+    hud.draw_fg_tile(DrawBundle {
+        x: 20,
+        y: 6,
+        id: TILE_ARROW,
+        flags: TileFlags::default(),
+        colors: Palette::new(0, 1, 6, 8),
+        size: SpriteSize::Normal,
+    });
+    (hud, map)
+}
+
+fn render_overlay(
+    main: (&VideoChip, &Bank, &Tilemap<100>),
+    hud: (&VideoChip, &Bank, &Tilemap<12>),
+    settings: Overlay,
+) -> Vec<RGBA32> {
+    let mut frame = vec![RGBA32::TRANSPARENT; SCREEN_W as usize * SCREEN_H as usize];
+    let (main_banks, main_maps) = ([main.1], [main.2]);
+    let (hud_banks, hud_maps) = ([hud.1], [hud.2]);
+    let main_iter = main.0.iter_pixels(&main_banks, &main_maps);
+    let hud_iter = hud.0.iter_pixels(&hud_banks, &hud_maps);
+    OverlayIter::new(main_iter, hud_iter, settings).render_frame(&mut frame);
+    frame
+}
+
+#[test]
+fn test_overlay_transparency() {
+    let mut video = new_video();
+    video.scroll = Vec2 { x: 6, y: 2 };
+    let bank = test_bank();
+    let map = test_tilemap();
+    let (hud, hud_map) = hud_video();
+    let main_frame = render(&video, &[&bank], &[&map]);
+    let mut hud_frame = vec![RGBA32::TRANSPARENT; HUD_W as usize * HUD_H as usize];
+    hud.render_frame(&[&bank], &[&hud_map], &mut hud_frame);
+
+    let (ox, oy) = (10, 24);
+    let settings = Overlay { x: ox, y: oy, ..Overlay::default() };
+    let frame = render_overlay((&video, &bank, &map), (&hud, &bank, &hud_map), settings);
+    let key = RGBA32::from(HUD_KEY);
+    for y in 0..SCREEN_H as i16 {
+        for x in 0..SCREEN_W as i16 {
+            let index = (y as usize * SCREEN_W as usize) + x as usize;
+            let (hx, hy) = (x - ox, y - oy);
+            let inside = hx >= 0 && hy >= 0 && hx < HUD_W as i16 && hy < HUD_H as i16;
+            let hud_pixel = inside.then(|| hud_frame[(hy as usize * HUD_W as usize) + hx as usize]);
+            let expected = match hud_pixel {
+                Some(pixel) if pixel != key => pixel,
+                _ => main_frame[index],
+            };
+            assert_eq!(frame[index], expected);
+        }
+    }
+
+    // The backdrop is the only place where the key color is used
+    let keyed = Overlay { transparency: Transparency::ColorKey(key), ..settings };
+    assert!(frame == render_overlay((&video, &bank, &map), (&hud, &bank, &hud_map), keyed));
+
+    // Opaque overlays hide the whole rectangle
+    let opaque = Overlay { transparency: Transparency::Opaque, ..settings };
+    let frame = render_overlay((&video, &bank, &map), (&hud, &bank, &hud_map), opaque);
+    for hy in 0..HUD_H as usize {
+        let start = ((hy + oy as usize) * SCREEN_W as usize) + ox as usize;
+        let hud_line = &hud_frame[hy * HUD_W as usize..(hy + 1) * HUD_W as usize];
+        assert_eq!(&frame[start..start + HUD_W as usize], hud_line);
+    }
+}
+
+#[test]
+fn test_overlay_order() {
+    // A main chip with an empty map is all backdrop, so the overlay behind it shows through
+    let video = new_video();
+    let bank = test_bank();
+    let map = Tilemap::<100>::new(10, 8);
+    let (hud, hud_map) = hud_video();
+    let behind = Overlay { x: -8, y: 40, in_front: false, ..Overlay::default() };
+    let frame = render_overlay((&video, &bank, &map), (&hud, &bank, &hud_map), behind);
+    let main_frame = render(&video, &[&bank], &[&map]);
+    let mut hud_frame = vec![RGBA32::TRANSPARENT; HUD_W as usize * HUD_H as usize];
+    hud.render_frame(&[&bank], &[&hud_map], &mut hud_frame);
+
+    let w = SCREEN_W as usize;
+    assert_eq!(frame[..40 * w], main_frame[..40 * w]);
+    for y in 40..SCREEN_H as usize {
+        let hud_line = &hud_frame[(y - 40) * HUD_W as usize..];
+        assert_eq!(frame[y * w..(y * w) + 40], hud_line[8..48]);
+        // The last column is outside the default viewport
+        assert_eq!(frame[(y * w) + 40..(y + 1) * w - 1], main_frame[(y * w) + 40..(y + 1) * w - 1]);
+    }
+
+    // The per pixel iterator must match
+    let (banks, maps, hud_maps) = ([&bank], [&map], [&hud_map]);
+    let main_iter = video.iter_pixels(&banks, &maps);
+    let hud_iter = hud.iter_pixels(&banks, &hud_maps);
+    let iterated = OverlayIter::new(main_iter, hud_iter, behind).collect::<Vec<_>>();
+    assert!(frame == iterated);
+}
+
+#[test]
+fn test_overlay_snapshot() {
+    let mut video = new_video();
+    video.scroll = Vec2 { x: 12, y: 4 };
+    video.draw_fg_tile(DrawBundle {
+        x: 30,
+        y: 20,
+        id: TILE_BLOCK,
+        flags: TileFlags::default(),
+        colors: Palette::new(0, 8, 7, 3),
+        size: SpriteSize::Zoom,
+    });
+    let bank = test_bank();
+    let map = test_tilemap();
+    let (hud, hud_map) = hud_video();
+    let settings = Overlay { x: 8, y: 34, ..Overlay::default() };
+    let frame = render_overlay((&video, &bank, &map), (&hud, &bank, &hud_map), settings);
+    assert_snapshot("overlay", &video, &frame);
+}
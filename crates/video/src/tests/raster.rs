@@ -0,0 +1,140 @@
+use super::snapshot::*;
+use super::*;
+
+/// Pixels of a single line.
+fn line(frame: &[RGBA32], y: usize) -> &[RGBA32] {
+    let w = SCREEN_W as usize;
+    &frame[y * w..(y + 1) * w]
+}
+
+fn hud_map() -> Tilemap<100> {
+    let mut map = Tilemap::<100>::new(10, 8);
+    let colors = Palette::new(0, 3, 11, 3);
+    for row in 0..8 {
+        for col in 0..10 {
+            map.set_cell(col, row, Cell { id: TILE_CHECKER, flags: TileFlags::default(), colors });
+        }
+    }
+    map
+}
+
+#[test]
+fn test_raster_scroll_table() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    video.scroll = Vec2 { x: 2, y: 1 };
+    video.raster.scroll.enabled = true;
+    for y in 0..SCREEN_H {
+        video.raster.scroll.set_line(y, (y % 5) as i16, -((y % 3) as i16));
+    }
+    let frame = render(&video, &[&bank], &[&map]);
+
+    // Offsets are added to the VideoChip scroll
+    video.raster.scroll.enabled = false;
+    for y in 0..SCREEN_H as usize {
+        video.scroll = Vec2 { x: 2 + (y % 5) as i16, y: 1 - (y % 3) as i16 };
+        let expected = render(&video, &[&bank], &[&map]);
+        assert_eq!(line(&frame, y), line(&expected, y));
+    }
+
+    // Waves stay within the amplitude
+    let mut table = ScrollTable::new();
+    table.set_wave(0, 48, 3.0, 16.0, 0.0);
+    assert!(table.lines[..48].iter().all(|offset| offset.x.abs() <= 3 && offset.y == 0));
+    assert!(table.lines[..48].iter().any(|offset| offset.x != 0));
+    assert!(table.lines[48..].iter().all(|offset| offset.x == 0));
+}
+
+#[test]
+fn test_raster_split() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    let hud = hud_map();
+    video.scroll = Vec2 { x: 5, y: 3 };
+    let game = render(&video, &[&bank], &[&map]);
+    video.scroll = Vec2::zero();
+    let status = render(&video, &[&bank], &[&hud]);
+
+    video.scroll = Vec2 { x: 5, y: 3 };
+    let split = RasterSplit {
+        line: 32,
+        bg_map_bank: 1,
+        bg_tile_bank: 0,
+        scroll: Some(Vec2::zero()),
+    };
+    assert!(video.raster.add_split(split));
+    let frame = render(&video, &[&bank], &[&map, &hud]);
+    for y in 0..SCREEN_H as usize {
+        let expected = if y < 32 { &game } else { &status };
+        assert_eq!(line(&frame, y), line(expected, y));
+    }
+
+    // Regions end where the next one starts, regardless of the order they were added
+    assert!(video.raster.add_split(RasterSplit { line: 40, bg_map_bank: 0, ..split }));
+    assert!(video.raster.add_split(RasterSplit { line: 8, bg_map_bank: 0, scroll: None, ..split }));
+    assert!(video.raster.add_split(split));
+    assert!(!video.raster.add_split(split));
+    assert_eq!(video.raster.split_at(4), None);
+    assert_eq!(video.raster.split_at(8).map(|s| s.line), Some(8));
+    assert_eq!(video.raster.split_at(39).map(|s| s.bg_map_bank), Some(1));
+    assert_eq!(video.raster.split_at(47).map(|s| s.line), Some(40));
+    video.raster.clear_splits();
+    assert!(!video.raster.is_active());
+}
+
+#[test]
+fn test_raster_gradient() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = Tilemap::<100>::new(10, 8);
+    video.raster.bg_colors.enabled = true;
+    video.raster.bg_colors.set_gradient(0, SCREEN_H, RGBA12::BLACK, RGBA12::new(7, 3, 0));
+    let lines = &video.raster.bg_colors.lines;
+    assert_eq!(lines[0], RGBA12::BLACK);
+    assert_eq!(lines[SCREEN_H as usize - 1], RGBA12::new(7, 3, 0));
+    assert!(lines[..SCREEN_H as usize].windows(2).all(|w| w[0].r() <= w[1].r()));
+
+    let frame = render(&video, &[&bank], &[&map]);
+    for y in 0..SCREEN_H as usize {
+        assert_eq!(line(&frame, y)[10], RGBA32::from(lines[y]));
+    }
+}
+
+#[test]
+fn test_irq_context() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    video.scroll = Vec2 { x: 7, y: 0 };
+    let expected = render(&video, &[&bank], &[&map]);
+
+    // The IRQ reads its parameters from the VideoChip instead of a global
+    video.scroll = Vec2::zero();
+    video.irq_context[3] = 7;
+    video.irq_line = Some(|iter, video, _map| {
+        iter.scroll_x = video.irq_context[3] as i16;
+    });
+    assert!(expected == render(&video, &[&bank], &[&map]));
+}
+
+#[test]
+fn test_raster_snapshot() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    let hud = hud_map();
+    video.raster.scroll.enabled = true;
+    video.raster.scroll.set_wave(0, 36, 2.0, 12.0, 3.0);
+    video.raster.bg_colors.enabled = true;
+    video.raster.bg_colors.set_gradient(0, 36, RGBA12::DARK_BLUE, RGBA12::LIGHT_BLUE);
+    video.raster.add_split(RasterSplit {
+        line: 36,
+        bg_map_bank: 1,
+        bg_tile_bank: 0,
+        scroll: Some(Vec2 { x: 0, y: 4 }),
+    });
+    let frame = render(&video, &[&bank], &[&map, &hud]);
+    assert_snapshot("raster", &video, &frame);
+}
@@ -0,0 +1,244 @@
+use super::snapshot::*;
+use super::*;
+use std::vec::Vec;
+
+#[test]
+fn test_bg_transforms() {
+    let video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("bg_transforms", &video, &frame);
+}
+
+#[test]
+fn test_bg_scroll_wrap() {
+    let mut video = new_video();
+    video.wrap_bg = true;
+    video.scroll = Vec2 { x: -13, y: 21 };
+    let bank = test_bank();
+    let map = test_tilemap();
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("bg_scroll_wrap", &video, &frame);
+}
+
+#[test]
+fn test_bg_scroll_no_wrap() {
+    let mut video = new_video();
+    video.wrap_bg = false;
+    video.scroll = Vec2 { x: 29, y: -11 };
+    let bank = test_bank();
+    let map = test_tilemap();
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("bg_scroll_no_wrap", &video, &frame);
+}
+
+#[test]
+fn test_viewport() {
+    let mut video = new_video();
+    video.crop_color = RGBA12::RED;
+    video.set_viewport(8, 4, 40, 30);
+    video.draw_fg_tile(DrawBundle {
+        x: 4,
+        y: 2,
+        id: TILE_BLOCK,
+        flags: TileFlags::default(),
+        colors: Palette::new(0, 8, 7, 3),
+        size: SpriteSize::Normal,
+    });
+    let bank = test_bank();
+    let map = test_tilemap();
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("viewport", &video, &frame);
+}
+
+#[test]
+fn test_sprites() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let mut map = test_tilemap();
+    // A foreground tile must cover the sprite drawn over it
+    map.set_cell(
+        2,
+        2,
+        Cell {
+            id: TILE_BLOCK,
+            flags: TileFlags::default().with_fg(true),
+            ..map.cells[0]
+        },
+    );
+
+    let colors = Palette::new(0, 1, 6, 8);
+    for i in 0..8u8 {
+        video.draw_fg_tile(DrawBundle {
+            x: (i as i16 * 9) - 4,
+            y: 4 + (i as i16 * 3),
+            id: TILE_ARROW,
+            flags: TileFlags::default().with_transform(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+            colors,
+            size: SpriteSize::Normal,
+        });
+    }
+    video.draw_fg_tile(DrawBundle {
+        x: 20,
+        y: 18,
+        id: TILE_CHECKER,
+        flags: TileFlags::default(),
+        colors: Palette::new(0, 3, 3, 3),
+        size: SpriteSize::Normal,
+    });
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("sprites", &video, &frame);
+}
+
+#[test]
+fn test_bg_planes() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    let mut back = Tilemap::<100>::new(8, 8);
+    let mut front = Tilemap::<100>::new(8, 8);
+    for row in 0..8 {
+        for col in 0..8 {
+            back.set_cell(
+                col,
+                row,
+                Cell {
+                    id: TILE_CHECKER,
+                    flags: TileFlags::default(),
+                    colors: Palette::new(0, 2, 2, 2),
+                },
+            );
+        }
+        front.set_cell(
+            row,
+            row,
+            Cell {
+                id: TILE_BLOCK,
+                flags: TileFlags::default(),
+                colors: Palette::new(0, 5, 6, 3),
+            },
+        );
+    }
+
+    video.bg_planes[0] = BgPlane {
+        scroll: Vec2 { x: 3, y: 5 },
+        wrap: true,
+        ..BgPlane::new(1, 0, PlanePriority::BehindMain)
+    };
+    video.bg_planes[1] = BgPlane::new(2, 0, PlanePriority::AboveSprites);
+    video.draw_fg_tile(DrawBundle {
+        x: 12,
+        y: 12,
+        id: TILE_CHECKER,
+        flags: TileFlags::default(),
+        colors: Palette::new(0, 15, 15, 15),
+        size: SpriteSize::Normal,
+    });
+    let frame = render(&video, &[&bank], &[&map, &back, &front]);
+    assert_snapshot("bg_planes", &video, &frame);
+}
+
+#[test]
+fn test_iter_matches_render_frame() {
+    let mut video = new_video();
+    video.wrap_bg = true;
+    video.scroll = Vec2 { x: 5, y: -7 };
+    video.draw_fg_tile(DrawBundle {
+        x: 30,
+        y: 20,
+        id: TILE_ARROW,
+        flags: TileFlags::default().with_flip_x(true),
+        colors: Palette::new(0, 1, 6, 8),
+        size: SpriteSize::Normal,
+    });
+    let bank = test_bank();
+    let map = test_tilemap();
+    let frame = render(&video, &[&bank], &[&map]);
+    let iterated = video.iter_pixels(&[&bank], &[&map]).collect::<Vec<_>>();
+    assert_eq!(frame, iterated);
+}
+
+#[test]
+fn test_sprite_priority() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+
+    // Row of sprites alternating between normal and behind BG priority
+    for i in 0..6i16 {
+        video.draw_fg_tile(DrawBundle {
+            x: 2 + (i * 10),
+            y: 6,
+            id: TILE_BLOCK,
+            flags: TileFlags::default().with_behind_bg(i % 2 == 0),
+            colors: Palette::new(0, 15, 8, 3),
+            size: SpriteSize::Normal,
+        });
+    }
+
+    // Multi-tile sprite behind the BG
+    let mut sprite = Tilemap::<4>::new(2, 2);
+    for row in 0..2 {
+        for col in 0..2 {
+            sprite.set_cell(col, row, Cell { id: TILE_CHECKER, ..Cell::default() });
+        }
+    }
+    video.draw_sprite(
+        SpriteBundle {
+            x: 20,
+            y: 24,
+            flip_x: false,
+            flip_y: false,
+            tile_offset: 0,
+            palette_override: Some(Palette::new(0, 3, 3, 3)),
+            behind_bg: true,
+        },
+        &sprite,
+    );
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("sprite_priority", &video, &frame);
+}
+
+#[test]
+fn test_bg_affine_identity() {
+    // The identity matrix must match the regular tile renderer exactly
+    let mut video = new_video();
+    video.wrap_bg = true;
+    video.scroll = Vec2 { x: -13, y: 21 };
+    let bank = test_bank();
+    let map = test_tilemap();
+    let regular = render(&video, &[&bank], &[&map]);
+    video.affine = Some(Affine::IDENTITY);
+    let affine = render(&video, &[&bank], &[&map]);
+    assert_eq!(regular, affine);
+}
+
+#[test]
+fn test_bg_affine_rotation() {
+    let mut video = new_video();
+    video.wrap_bg = false;
+    video.affine = Some(Affine::rotate_zoom(0.5, 1.5).with_origin(32, 24));
+    let bank = test_bank();
+    let map = test_tilemap();
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("bg_affine_rotation", &video, &frame);
+}
+
+#[test]
+fn test_bg_affine_perspective() {
+    let mut video = new_video();
+    video.wrap_bg = true;
+    video.affine = Some(Affine::IDENTITY);
+    // Pseudo-3D floor: lines further down the screen are closer to the camera
+    video.irq_line = Some(|iter, _video, _map| {
+        // Each line samples a single map row, at a depth that shrinks towards the horizon
+        let depth = 1024 / (iter.y() as i16 + 8);
+        let scale = AFFINE_ONE * 16 / (iter.y() as i16 + 8);
+        iter.affine = Some(Affine::new(scale, 0, 0, 0).with_origin(32, depth));
+    });
+    let bank = test_bank();
+    let map = test_tilemap();
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("bg_affine_perspective", &video, &frame);
+}
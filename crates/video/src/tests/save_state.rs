@@ -0,0 +1,223 @@
+use super::snapshot::*;
+use super::*;
+use std::vec;
+
+/// A chip with most registers away from their defaults, and a few sprites.
+fn busy_video() -> VideoChip {
+    let mut video = new_video();
+    video.scroll = Vec2 { x: 5, y: -3 };
+    video.wrap_bg = true;
+    video.crop_color = RGBA12::RED;
+    video.color_math.brightness = -64;
+    video.color_math.sprite_blend = BlendMode::Average;
+    video.mosaic = Mosaic::new(2, 1);
+    video.irq_x_trigger = IrqTrigger::at(&[16, 40]);
+    video.irq_context = [1, -2, 3, -4, 5, -6, 7, -8];
+    video.raster.scroll.enabled = true;
+    video.raster.scroll.set_wave(0, SCREEN_H, 3.0, 16.0, 0.0);
+    video.raster.add_split(RasterSplit {
+        line: 40,
+        bg_map_bank: 0,
+        bg_tile_bank: 0,
+        scroll: Some(Vec2 { x: 0, y: 0 }),
+    });
+    video.windows[0].enabled = true;
+    video.windows[0].sprites = WindowMask::Inside;
+    video.windows[0].set_rect(0, 0, 12, 48);
+    video.detect_collisions = true;
+    video.set_viewport(2, 2, 58, 42);
+    video.frame_number = 1234;
+    for (i, size) in
+        [SpriteSize::Normal, SpriteSize::Tall, SpriteSize::Zoom].into_iter().enumerate()
+    {
+        video.draw_fg_tile(DrawBundle {
+            x: 8 + i as i16 * 18,
+            y: 12,
+            id: TILE_ARROW,
+            flags: TileFlags::default().with_transform(i == 1, false, i == 2),
+            colors: Palette::new(0, 1, 6, 8),
+            size,
+        });
+    }
+    video
+}
+
+#[test]
+fn test_video_round_trip() {
+    let video = busy_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    let expected = render(&video, &[&bank], &[&map]);
+
+    let mut buffer = vec![0u8; video.state_len()];
+    let len = video.save_state(&mut buffer).unwrap();
+    assert_eq!(len, buffer.len());
+
+    let mut restored = VideoChip::new(32, 16, 30);
+    assert_eq!(restored.load_state(&buffer), Ok(len));
+    assert_eq!(restored.width(), SCREEN_W);
+    assert_eq!(restored.height(), SCREEN_H);
+    assert_eq!(restored.frame_number(), 1234);
+    assert_eq!(restored.irq_context, video.irq_context);
+    assert_eq!(restored.sprite_stats(), video.sprite_stats());
+    assert_eq!(render(&restored, &[&bank], &[&map]), expected);
+
+    // Saving the restored chip produces the exact same bytes
+    let mut again = vec![0u8; len];
+    restored.save_state(&mut again).unwrap();
+    assert_eq!(again, buffer);
+}
+
+#[test]
+fn test_bank_and_tilemap_round_trip() {
+    let mut bank = test_bank();
+    bank.tiles.set_anim(TileAnim::new(TILE_ARROW, 4, &[TILE_ARROW, TILE_CHECKER]));
+    bank.colors.add_cycle(ColorCycle::new(4, 7, 10).with_mode(CycleMode::PingPong)).unwrap();
+    bank.push_checkpoint().unwrap();
+    let map = test_tilemap();
+
+    // Both states share a buffer, one after the other
+    let mut buffer = vec![0u8; bank.state_len() + map.state_len()];
+    let bank_len = bank.save_state(&mut buffer).unwrap();
+    let map_len = map.save_state(&mut buffer[bank_len..]).unwrap();
+    assert_eq!(bank_len + map_len, buffer.len());
+
+    let mut restored_bank = Bank::new_16_colors();
+    let mut restored_map = Tilemap::<100>::new(2, 2);
+    restored_bank.load_state(&buffer).unwrap();
+    restored_map.load_state(&buffer[bank_len..]).unwrap();
+
+    assert_eq!(restored_bank.tiles.mode(), TileMode::Colors4);
+    assert_eq!(restored_bank.tiles.count(), bank.tiles.count());
+    assert_eq!(restored_bank.tiles.anims(), bank.tiles.anims());
+    assert_eq!(restored_bank.colors.palette, bank.colors.palette);
+    assert_eq!(restored_bank.colors.cycle(CycleID(0)), bank.colors.cycle(CycleID(0)));
+    assert_eq!(restored_bank.checkpoint_depth(), 1);
+    // The version isn't restored, but bumped so cached tiles are refreshed
+    assert_ne!(restored_bank.tiles.version(), Bank::new_16_colors().tiles.version());
+    assert_eq!(restored_map.cells, map.cells);
+    assert_eq!((restored_map.columns, restored_map.rows), (map.columns, map.rows));
+
+    let video = new_video();
+    assert_eq!(
+        render(&video, &[&restored_bank], &[&restored_map]),
+        render(&video, &[&bank], &[&map])
+    );
+}
+
+#[test]
+fn test_invalid_states() {
+    let map = test_tilemap();
+    let mut buffer = vec![0u8; map.state_len()];
+    assert_eq!(map.save_state(&mut buffer[..20]), Err("Buffer is too small for save state"));
+    map.save_state(&mut buffer).unwrap();
+
+    // Too small for the saved map
+    let mut small = Tilemap::<16>::new(4, 4);
+    assert!(small.load_state(&buffer).is_err());
+    // Loaded into a different type
+    assert_eq!(test_bank().load_state(&buffer), Err("Save state belongs to a different type"));
+    // Truncated
+    let mut target = Tilemap::<100>::new(10, 8);
+    assert_eq!(target.load_state(&buffer[..buffer.len() - 1]), Err("Save state is truncated"));
+    // Written by a different version
+    let mut old = buffer.clone();
+    old[4..6].copy_from_slice(&(SAVE_STATE_VERSION + 1).to_le_bytes());
+    assert_eq!(target.load_state(&old), Err("Unsupported save state version"));
+    // Not a save state at all
+    assert_eq!(target.load_state(&[0; 32]), Err("Not a save state"));
+    // None of the failures above modified the target
+    assert_eq!(target.cells, Tilemap::<100>::new(10, 8).cells);
+}
+
+#[test]
+fn test_invalid_bank_states() {
+    let load = |bank: &Bank| {
+        let mut buffer = vec![0u8; bank.state_len()];
+        bank.save_state(&mut buffer).unwrap();
+        Bank::new().load_state(&buffer)
+    };
+    let bank = || {
+        let mut bank = test_bank();
+        bank.tiles.set_anim(TileAnim::new(TILE_ARROW, 4, &[TILE_ARROW, TILE_CHECKER]));
+        bank.push_checkpoint().unwrap();
+        bank
+    };
+    assert!(load(&bank()).is_ok());
+
+    // Checkpoints beyond the bank's capacity
+    let mut invalid = bank();
+    invalid.tiles.checkpoints[0].colors = COLORS_PER_PALETTE + 1;
+    assert_eq!(load(&invalid), Err("Invalid value in save state"));
+    let mut bank_16 = Bank::new_16_colors();
+    bank_16.tiles.checkpoints[0].tiles = TILE_COUNT as u16;
+    bank_16.tiles.checkpoint_count = 1;
+    assert_eq!(load(&bank_16), Err("Invalid value in save state"));
+
+    // Animation frames beyond the capacity of a 16 color bank
+    let mut bank_16 = Bank::new_16_colors();
+    bank_16.tiles.set_anim(TileAnim::new(TileID(1), 4, &[TileID(1), TileID(130)]));
+    assert_eq!(load(&bank_16), Err("Invalid value in save state"));
+
+    // Lookup entries that don't point back at an animation for the same tile
+    let mut invalid = bank();
+    invalid.tiles.anim_lookup[TILE_BLOCK.0 as usize] = 1;
+    assert_eq!(load(&invalid), Err("Invalid value in save state"));
+    let mut invalid = bank();
+    invalid.tiles.anim_lookup[TILE_ARROW.0 as usize] = 0;
+    assert_eq!(load(&invalid), Err("Invalid value in save state"));
+
+    // A rejected state leaves the bank untouched
+    let mut buffer = vec![0u8; invalid.state_len()];
+    invalid.save_state(&mut buffer).unwrap();
+    let mut target = test_bank();
+    let version = target.tiles.version();
+    assert!(target.load_state(&buffer).is_err());
+    assert_eq!(target.tiles.version(), version);
+    assert_eq!(target.tiles.anims(), test_bank().tiles.anims());
+    let video = new_video();
+    let map = test_tilemap();
+    assert_eq!(render(&video, &[&target], &[&map]), render(&video, &[&test_bank()], &[&map]));
+}
+
+#[test]
+fn test_invalid_video_states() {
+    let load = |video: &VideoChip, target: &mut VideoChip| {
+        let mut buffer = vec![0u8; video.state_len()];
+        video.save_state(&mut buffer).unwrap();
+        target.load_state(&buffer)
+    };
+    let bank = test_bank();
+    let map = test_tilemap();
+    let mut target = busy_video();
+    let expected = render(&target, &[&bank], &[&map]);
+
+    let mut invalid = new_video();
+    invalid.fg_tile_bank = BANK_COUNT as u8;
+    assert_eq!(load(&invalid, &mut target), Err("Invalid value in save state"));
+    let mut invalid = new_video();
+    invalid.fg_color_bank = Some(BANK_COUNT as u8);
+    assert_eq!(load(&invalid, &mut target), Err("Invalid value in save state"));
+    let mut invalid = new_video();
+    invalid.bg_planes[1].map_bank = BG_BANK_COUNT as u8;
+    assert_eq!(load(&invalid, &mut target), Err("Invalid value in save state"));
+    let mut invalid = new_video();
+    invalid.raster.add_split(RasterSplit {
+        line: 8,
+        bg_map_bank: 0,
+        bg_tile_bank: BANK_COUNT as u8,
+        scroll: None,
+    });
+    assert_eq!(load(&invalid, &mut target), Err("Invalid value in save state"));
+    let mut invalid = new_video();
+    invalid.view_right = SCREEN_W + 1;
+    assert_eq!(load(&invalid, &mut target), Err("Invalid value in save state"));
+    let mut invalid = new_video();
+    invalid.view_top = 20;
+    invalid.view_bottom = 10;
+    assert_eq!(load(&invalid, &mut target), Err("Invalid value in save state"));
+
+    // None of the failures above modified the target
+    assert_eq!(target.frame_number(), 1234);
+    assert_eq!(render(&target, &[&bank], &[&map]), expected);
+}
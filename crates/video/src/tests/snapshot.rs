@@ -0,0 +1,172 @@
+use super::*;
+use std::{format, fs, path::PathBuf, string::String, vec, vec::Vec};
+
+pub const SCREEN_W: u16 = 64;
+pub const SCREEN_H: u16 = 48;
+
+pub const TILE_EMPTY: TileID = TileID(0);
+pub const TILE_ARROW: TileID = TileID(1);
+pub const TILE_CHECKER: TileID = TileID(2);
+pub const TILE_BLOCK: TileID = TileID(3);
+
+/// Converts 8 rows of digits (palette slots 0 to 3) into a tile.
+pub fn tile_from_rows(rows: [&str; 8]) -> Tile<2> {
+    let mut tile = Tile::<2>::default();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, ch) in row.bytes().enumerate() {
+            tile.set_pixel(x as u8, y as u8, ch - b'0');
+        }
+    }
+    tile
+}
+
+/// A bank with the default colors and a few asymmetric tiles, so that
+/// any flip or rotation mistake is visible in the output.
+pub fn test_bank() -> Bank {
+    let mut bank = Bank::new();
+    bank.colors.load_default();
+    bank.append_tile(&Tile::default()).unwrap();
+    bank.append_tile(&tile_from_rows([
+        "11111110", //
+        "12222210", "12333100", "12331000", "12310300", "12100030", "11000003", "00000000",
+    ]))
+    .unwrap();
+    bank.append_tile(&tile_from_rows([
+        "20202020", //
+        "02020202", "20202020", "02020202", "20202020", "02020202", "20202020", "02020202",
+    ]))
+    .unwrap();
+    bank.append_tile(&tile_from_rows([
+        "33333333", //
+        "32222223", "32111123", "32111123", "32111123", "32111123", "32222223", "33333333",
+    ]))
+    .unwrap();
+    bank
+}
+
+/// A tilemap larger than the screen, using every combination of transform flags.
+pub fn test_tilemap() -> Tilemap<100> {
+    let mut map = Tilemap::<100>::new(10, 8);
+    let palettes = [
+        Palette::new(0, 1, 5, 8),
+        Palette::new(0, 12, 13, 14),
+        Palette::new(0, 9, 10, 11),
+        Palette::new(0, 4, 7, 15),
+    ];
+    for row in 0..map.rows as i16 {
+        for col in 0..map.columns as i16 {
+            let index = (row * map.columns as i16 + col) as usize;
+            let flags =
+                TileFlags::default().with_transform(index & 1 != 0, index & 2 != 0, index & 4 != 0);
+            let id = if (col + row) % 5 == 4 { TILE_EMPTY } else { TILE_ARROW };
+            map.set_cell(col, row, Cell { id, flags, colors: palettes[index % palettes.len()] });
+        }
+    }
+    map
+}
+
+pub fn new_video() -> VideoChip {
+    let mut video = VideoChip::new(SCREEN_W, SCREEN_H, 60);
+    video.bg_color = RGBA12::new(1, 1, 2);
+    video
+}
+
+/// Renders a full frame using the headless renderer.
+pub fn render<'a, T>(video: &'a VideoChip, banks: &'a [&'a Bank], maps: &'a [&'a T]) -> Vec<RGBA32>
+where
+    &'a T: Into<TilemapRef<'a>>,
+{
+    let mut frame = vec![RGBA32::TRANSPARENT; video.width() as usize * video.height() as usize];
+    video.render_frame(banks, maps, &mut frame);
+    frame
+}
+
+fn snapshot_path(name: &str, suffix: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots").join(format!("{name}{suffix}.ppm"))
+}
+
+/// Binary PPM (P6). Alpha is discarded, so scenes should use an opaque BG color.
+fn write_ppm(path: &PathBuf, w: u16, h: u16, pixels: &[RGBA32]) {
+    let mut data = format!("P6\n{} {}\n255\n", w, h).into_bytes();
+    for pixel in pixels {
+        data.extend_from_slice(&[pixel.r, pixel.g, pixel.b]);
+    }
+    fs::write(path, data).unwrap();
+}
+
+fn read_ppm(path: &PathBuf) -> Option<(u16, u16, Vec<RGBA32>)> {
+    let data = fs::read(path).ok()?;
+    // Header is always "P6", width, height and max value, separated by whitespace
+    let mut fields = Vec::new();
+    let mut cursor = 0;
+    while fields.len() < 4 {
+        while data.get(cursor)?.is_ascii_whitespace() {
+            cursor += 1;
+        }
+        let start = cursor;
+        while !data.get(cursor)?.is_ascii_whitespace() {
+            cursor += 1;
+        }
+        fields.push(String::from_utf8(data[start..cursor].to_vec()).ok()?);
+    }
+    cursor += 1;
+    if fields[0] != "P6" || fields[3] != "255" {
+        return None;
+    }
+    let w: u16 = fields[1].parse().ok()?;
+    let h: u16 = fields[2].parse().ok()?;
+    let pixels = data[cursor..]
+        .chunks_exact(3)
+        .map(|rgb| RGBA32::new(rgb[0], rgb[1], rgb[2]))
+        .collect::<Vec<_>>();
+    (pixels.len() == w as usize * h as usize).then_some((w, h, pixels))
+}
+
+/// Compares a rendered frame against its reference image. On mismatch, the actual
+/// output and a diff image (mismatched pixels in red) are saved next to the reference.
+pub fn assert_snapshot(name: &str, video: &VideoChip, pixels: &[RGBA32]) {
+    let (w, h) = (video.width(), video.height());
+    let path = snapshot_path(name, "");
+    // Leftovers from a previous failure
+    let _ = fs::remove_file(snapshot_path(name, ".actual"));
+    let _ = fs::remove_file(snapshot_path(name, ".diff"));
+
+    if std::env::var_os("TATO_UPDATE_SNAPSHOTS").is_some() {
+        write_ppm(&path, w, h, pixels);
+        return;
+    }
+
+    let Some((ref_w, ref_h, reference)) = read_ppm(&path) else {
+        write_ppm(&snapshot_path(name, ".actual"), w, h, pixels);
+        panic!(
+            "Missing or invalid reference image {:?}. Set TATO_UPDATE_SNAPSHOTS to create it.",
+            path
+        );
+    };
+    assert_eq!((ref_w, ref_h), (w, h), "Snapshot '{}' has a different resolution", name);
+
+    let mut mismatches = 0;
+    let diff = pixels
+        .iter()
+        .zip(reference.iter())
+        .map(|(actual, expected)| {
+            if (actual.r, actual.g, actual.b) == (expected.r, expected.g, expected.b) {
+                RGBA32::new(expected.r / 4, expected.g / 4, expected.b / 4)
+            } else {
+                mismatches += 1;
+                RGBA32::RED
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if mismatches > 0 {
+        write_ppm(&snapshot_path(name, ".actual"), w, h, pixels);
+        write_ppm(&snapshot_path(name, ".diff"), w, h, &diff);
+        panic!(
+            "Snapshot '{}' differs from reference in {} pixels, see {:?}",
+            name,
+            mismatches,
+            snapshot_path(name, ".diff")
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use super::snapshot::*;
+use super::*;
+
+fn draw_row(video: &mut VideoChip, count: i16, y: i16) {
+    for i in 0..count {
+        video.draw_fg_tile(DrawBundle {
+            x: i * 2,
+            y,
+            id: TILE_BLOCK,
+            flags: TileFlags::default(),
+            colors: Palette::default(),
+            size: SpriteSize::Normal,
+        });
+    }
+}
+
+#[test]
+fn test_scanline_overflow_stats() {
+    let mut video = new_video();
+    draw_row(&mut video, SPRITES_PER_LINE as i16 + 4, 10);
+
+    let stats = video.sprite_stats();
+    assert_eq!(stats.sprite_count, SPRITES_PER_LINE as u16 + 4);
+    assert_eq!(stats.dropped_sprites, 0);
+    assert_eq!(stats.overflowed_lines, TILE_SIZE as u16);
+    assert_eq!(video.sprite_overflow(9), 0);
+    assert_eq!(video.sprite_overflow(10), 4);
+    assert_eq!(video.sprite_overflow(17), 4);
+    assert_eq!(video.sprite_overflow(18), 0);
+    assert!(video.overflowed_lines().map(|(y, _)| y).eq(10..18));
+
+    // Stats are cleared on every new frame
+    video.frame_start(false);
+    assert_eq!(video.sprite_stats(), SpriteStats::default());
+    assert_eq!(video.overflowed_lines().count(), 0);
+}
+
+#[test]
+fn test_sprite_table_overflow() {
+    let mut video = new_video();
+    for i in 0..300 {
+        video.draw_fg_tile(DrawBundle {
+            x: i % SCREEN_W as i16,
+            y: (i / 8) % SCREEN_H as i16,
+            id: TILE_BLOCK,
+            flags: TileFlags::default(),
+            colors: Palette::default(),
+            size: SpriteSize::Normal,
+        });
+    }
+    let stats = video.sprite_stats();
+    assert_eq!(stats.sprite_count + stats.dropped_sprites, 300);
+    assert!(stats.dropped_sprites > 0);
+}
+
+#[test]
+fn test_sprite_flicker() {
+    let count = SPRITES_PER_LINE as i16 + 4;
+    let visible = |video: &VideoChip| {
+        let line = &video.sprite_gen.scanlines[10];
+        let mut result = [false; MAX_SPRITES];
+        for &id in &line.sprites[..line.sprite_count as usize] {
+            result[id as usize] = true;
+        }
+        result
+    };
+
+    // Without flicker, the same sprites are always dropped
+    let mut video = new_video();
+    draw_row(&mut video, count, 10);
+    let first = visible(&video);
+    video.frame_start(false);
+    draw_row(&mut video, count, 10);
+    assert_eq!(first, visible(&video));
+    assert!(!first[count as usize - 1]);
+
+    // With flicker, dropped sprites alternate and every sprite is eventually visible
+    video.sprite_flicker = true;
+    let mut ever_visible = [false; MAX_SPRITES];
+    for _ in 0..4 {
+        video.frame_start(false);
+        draw_row(&mut video, count, 10);
+        assert_eq!(video.sprite_gen.scanlines[10].sprite_count as usize, SPRITES_PER_LINE);
+        assert_eq!(video.sprite_overflow(10), 4);
+        for (ever, now) in ever_visible.iter_mut().zip(visible(&video)) {
+            *ever |= now;
+        }
+    }
+    assert!(ever_visible[..count as usize].iter().all(|&v| v));
+
+    // Drawing order must still follow insertion order
+    let line = &video.sprite_gen.scanlines[10];
+    assert!(line.sprites[..line.sprite_count as usize].windows(2).all(|w| w[0] < w[1]));
+}
+
+fn draw_sized(
+    video: &mut VideoChip,
+    x: i16,
+    y: i16,
+    id: TileID,
+    flags: TileFlags,
+    size: SpriteSize,
+) {
+    video.draw_fg_tile(DrawBundle { x, y, id, flags, colors: Palette::new(0, 1, 6, 8), size });
+}
+
+#[test]
+fn test_sprite_sizes_match_tiles() {
+    let bank = test_bank();
+    let map = Tilemap::<100>::new(10, 8);
+    let flip_y = TileFlags::default().with_flip_y(true);
+
+    // Tall sprites are two consecutive tiles, swapped when flipped vertically
+    let mut video = new_video();
+    draw_sized(&mut video, 8, 8, TILE_ARROW, TileFlags::default(), SpriteSize::Tall);
+    draw_sized(&mut video, 24, 8, TILE_ARROW, flip_y, SpriteSize::Tall);
+    let tall = render(&video, &[&bank], &[&map]);
+
+    let mut video = new_video();
+    draw_sized(&mut video, 8, 8, TILE_ARROW, TileFlags::default(), SpriteSize::Normal);
+    draw_sized(&mut video, 8, 16, TILE_CHECKER, TileFlags::default(), SpriteSize::Normal);
+    draw_sized(&mut video, 24, 8, TILE_CHECKER, flip_y, SpriteSize::Normal);
+    draw_sized(&mut video, 24, 16, TILE_ARROW, flip_y, SpriteSize::Normal);
+    assert!(tall == render(&video, &[&bank], &[&map]));
+
+    // Zoomed sprites repeat every tile pixel in a 2x2 block
+    let mut video = new_video();
+    draw_sized(&mut video, 0, 0, TILE_ARROW, TileFlags::default(), SpriteSize::Normal);
+    let normal = render(&video, &[&bank], &[&map]);
+    let mut video = new_video();
+    draw_sized(&mut video, 20, 10, TILE_ARROW, TileFlags::default(), SpriteSize::Zoom);
+    let zoom = render(&video, &[&bank], &[&map]);
+    for y in 0..16 {
+        for x in 0..16 {
+            let expected = normal[(y / 2) * SCREEN_W as usize + (x / 2)];
+            assert_eq!(zoom[(y + 10) * SCREEN_W as usize + (x + 20)], expected);
+        }
+    }
+}
+
+#[test]
+fn test_sprite_size_scanlines() {
+    let mut video = new_video();
+    let tall = video.draw_fg_tile(DrawBundle {
+        x: 0,
+        y: 4,
+        id: TILE_BLOCK,
+        flags: TileFlags::default(),
+        colors: Palette::default(),
+        size: SpriteSize::Tall,
+    });
+    assert!(tall.is_some());
+    let occupied = |video: &VideoChip, y: usize| video.sprite_gen.scanlines[y].sprite_count;
+    assert_eq!(occupied(&video, 3), 0);
+    assert_eq!(occupied(&video, 19), 1);
+    assert_eq!(occupied(&video, 20), 0);
+
+    // Partially visible on the left edge, 16 pixels wide
+    video.frame_start(false);
+    let zoom = video.draw_fg_tile(DrawBundle {
+        x: -12,
+        y: 0,
+        id: TILE_BLOCK,
+        flags: TileFlags::default(),
+        colors: Palette::default(),
+        size: SpriteSize::Zoom,
+    });
+    assert!(zoom.is_some());
+    assert_eq!(occupied(&video, 15), 1);
+    assert_eq!(occupied(&video, 16), 0);
+}
+
+#[test]
+fn test_tall_sprite_last_tile() {
+    let mut video = new_video();
+    let bundle = DrawBundle {
+        x: 8,
+        y: 8,
+        id: TileID(255),
+        flags: TileFlags::default(),
+        colors: Palette::default(),
+        size: SpriteSize::Tall,
+    };
+    assert!(video.draw_fg_tile(bundle).is_none());
+    assert!(video.draw_fg_tile(DrawBundle { size: SpriteSize::Normal, ..bundle }).is_some());
+
+    // In a 16 color bank the bottom tile of ID 127 would wrap around to tile 0
+    let mut bank = Bank::new_16_colors();
+    bank.colors.load_default();
+    let mut solid = Tile::<4>::default();
+    for y in 0..TILE_SIZE {
+        for x in 0..TILE_SIZE {
+            solid.set_pixel(x, y, 5);
+        }
+    }
+    bank.append_tile_16(&solid).unwrap();
+    let blank = bank.append_tile_16(&Tile::default()).unwrap();
+    let mut map = Tilemap::<100>::new(10, 8);
+    for cell in &mut map.cells {
+        cell.id = blank;
+    }
+    let mut video = new_video();
+    let empty = render(&video, &[&bank], &[&map]);
+    assert!(video.draw_fg_tile(DrawBundle { id: TileID(127), ..bundle }).is_some());
+    assert!(render(&video, &[&bank], &[&map]) == empty);
+    video.draw_fg_tile(DrawBundle { id: TileID(0), x: 24, ..bundle });
+    assert!(render(&video, &[&bank], &[&map]) != empty);
+}
+
+#[test]
+fn test_sprite_sizes_snapshot() {
+    let mut video = new_video();
+    let bank = test_bank();
+    let map = test_tilemap();
+    let rotated = TileFlags::default().with_rotation(true);
+    draw_sized(&mut video, 4, 4, TILE_ARROW, TileFlags::default(), SpriteSize::Tall);
+    draw_sized(&mut video, 16, 4, TILE_ARROW, rotated.with_flip_x(true), SpriteSize::Tall);
+    draw_sized(&mut video, 28, 4, TILE_ARROW, TileFlags::default(), SpriteSize::Zoom);
+    draw_sized(&mut video, 44, 20, TILE_ARROW, rotated, SpriteSize::Zoom);
+    draw_sized(&mut video, 4, 28, TILE_CHECKER, TileFlags::default(), SpriteSize::Zoom);
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("sprite_sizes", &video, &frame);
+}
+
+#[test]
+fn test_sprite_color_bank() {
+    let bank = test_bank();
+    // Same color slots, different colors, and no tiles at all
+    let mut sprite_colors = Bank::new();
+    sprite_colors.colors.load_default();
+    sprite_colors.colors.palette[1] = RGBA12::new(7, 0, 7);
+    sprite_colors.colors.palette[8] = RGBA12::new(0, 7, 7);
+    let map = test_tilemap();
+
+    let mut video = new_video();
+    let colors = Palette::new(0, 1, 6, 8);
+    video.draw_fg_tile(DrawBundle {
+        x: 16,
+        y: 16,
+        id: TILE_BLOCK,
+        flags: TileFlags::default(),
+        colors,
+        size: SpriteSize::Normal,
+    });
+    let shared = render(&video, &[&bank, &sprite_colors], &[&map]);
+    video.fg_color_bank = Some(1);
+    let separate = render(&video, &[&bank, &sprite_colors], &[&map]);
+
+    let pixel = |frame: &[RGBA32], x: usize, y: usize| frame[y * SCREEN_W as usize + x];
+    // Border uses palette slot 3, the center uses slot 1
+    assert_eq!(pixel(&shared, 16, 16), RGBA32::from(bank.colors.palette[8]));
+    assert_eq!(pixel(&separate, 16, 16), RGBA32::from(RGBA12::new(0, 7, 7)));
+    assert_eq!(pixel(&separate, 19, 19), RGBA32::from(RGBA12::new(7, 0, 7)));
+    // The BG still uses its own bank's colors
+    for (index, (a, b)) in shared.iter().zip(&separate).enumerate() {
+        let (x, y) = (index % SCREEN_W as usize, index / SCREEN_W as usize);
+        if !(16..24).contains(&x) || !(16..24).contains(&y) {
+            assert_eq!(a, b);
+        }
+    }
+}
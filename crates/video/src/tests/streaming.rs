@@ -0,0 +1,67 @@
+use super::snapshot::*;
+use super::*;
+
+/// A world much larger than the streaming buffer.
+fn test_world() -> Tilemap<2400> {
+    let mut world = Tilemap::<2400>::new(60, 40);
+    let pattern = test_tilemap();
+    for row in 0..world.rows as i16 {
+        for col in 0..world.columns as i16 {
+            let mut cell = pattern.get_cell(col % 10, row % 8).unwrap();
+            cell.id = [TILE_ARROW, TILE_CHECKER, TILE_BLOCK, TILE_EMPTY]
+                [(col / 3 + row / 2) as usize % 4];
+            world.set_cell(col, row, cell);
+        }
+    }
+    world
+}
+
+#[test]
+fn test_streaming_matches_world() {
+    let bank = test_bank();
+    let mut world = test_world();
+    let mut stream = StreamingTilemap::<100>::for_viewport(SCREEN_W, SCREEN_H);
+    assert_eq!((stream.map.columns, stream.map.rows), (9, 7));
+
+    let mut video = new_video();
+    assert!(stream.follow(&video, &mut world).is_err());
+    assert_eq!(stream.origin(), None);
+
+    let path =
+        [(0, 0), (3, 1), (12, 9), (29, 8), (29, -5), (-20, -7), (150, 100), (152, 101), (444, 290)];
+    for (x, y) in path {
+        video.scroll = Vec2 { x, y };
+        video.wrap_bg = false;
+        let expected = render(&video, &[&bank], &[&world]);
+
+        video.wrap_bg = true;
+        stream.follow(&video, &mut world).unwrap();
+        let frame = render(&video, &[&bank], &[&stream]);
+        assert!(frame == expected, "Streamed frame differs from world at scroll {x}, {y}");
+    }
+}
+
+#[test]
+fn test_streaming_loads_only_new_cells() {
+    let mut stream = StreamingTilemap::<100>::new(9, 7);
+    let mut requests = 0;
+    let mut source = |col: i16, row: i16| {
+        requests += 1;
+        Cell::new((col + row) as u8, 0, 0)
+    };
+
+    assert_eq!(stream.update(Vec2 { x: 0, y: 0 }, &mut source), 63);
+    // Scrolling inside the same tile loads nothing
+    assert_eq!(stream.update(Vec2 { x: 7, y: 7 }, &mut source), 0);
+    // One new column, then one new column and one new row (sharing a corner)
+    assert_eq!(stream.update(Vec2 { x: 8, y: 7 }, &mut source), 7);
+    assert_eq!(stream.update(Vec2 { x: 16, y: 8 }, &mut source), 7 + 8);
+    assert_eq!(stream.update(Vec2 { x: -8, y: 0 }, &mut source), 3 * 7 + 6);
+    assert_eq!(stream.origin(), Some(Vec2 { x: -1, y: 0 }));
+    // Jumps larger than the buffer reload everything
+    assert_eq!(stream.update(Vec2 { x: 800, y: 0 }, &mut source), 63);
+    stream.invalidate();
+    assert_eq!(stream.update(Vec2 { x: 800, y: 0 }, &mut source), 63);
+    assert_eq!(requests, 63 * 3 + 7 + 15 + 27);
+    assert_eq!(stream.map.get_id(100 % 9, 3).unwrap(), TileID(103));
+}
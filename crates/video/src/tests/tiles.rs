@@ -0,0 +1,334 @@
+use super::snapshot::*;
+use super::*;
+
+/// A 16 color tile where every pixel uses a different color from its neighbours.
+fn gradient_tile() -> Tile<4> {
+    let mut tile = Tile::<4>::default();
+    for y in 0..TILE_SIZE {
+        for x in 0..TILE_SIZE {
+            tile.set_pixel(x, y, (x + y * 3) % 16);
+        }
+    }
+    tile
+}
+
+fn bank_16() -> Bank {
+    let mut bank = Bank::new_16_colors();
+    bank.colors.load_default();
+    bank.append_tile_16(&Tile::default()).unwrap();
+    bank.append_tile_16(&gradient_tile()).unwrap();
+    bank
+}
+
+#[test]
+fn test_tile_16_storage() {
+    let bank = bank_16();
+    let tile = gradient_tile();
+    assert_eq!(bank.tiles.mode(), TileMode::Colors16);
+    assert_eq!(bank.tiles.count(), 2);
+    assert_eq!(bank.tiles.capacity(), TILE_COUNT / 2);
+    assert_eq!(bank.tiles.get_16(TileID(1)), tile);
+    for y in 0..TILE_SIZE {
+        for x in 0..TILE_SIZE {
+            assert_eq!(bank.tiles.get_pixel(TileID(1), x, y), tile.get_pixel(x, y));
+        }
+    }
+    // Const construction matches runtime appending
+    let from_const = TileBank::from_tiles_16(&[Tile::default(), tile]);
+    assert_eq!(from_const.tiles, bank.tiles.tiles);
+}
+
+#[test]
+fn test_tile_16_rows_and_wrapping() {
+    let bank = bank_16();
+    let tile = gradient_tile();
+    for index in 0..8u8 {
+        let flags =
+            TileFlags::default().with_transform(index & 1 != 0, index & 2 != 0, index & 4 != 0);
+        for row in 0..TILE_SIZE {
+            let expected: [u8; 8] = core::array::from_fn(|x| {
+                let (tx, ty) = flags.transform_coords(x as u8, row, TILE_SIZE);
+                tile.get_pixel(tx, ty)
+            });
+            let colors = bank.tiles.row_colors(TileID(1), flags, row, Palette::default());
+            assert_eq!(colors, expected);
+        }
+    }
+
+    // Only half of the TileIDs fit in a 16 color bank, the rest wrap around
+    assert_eq!(bank.tiles.get_pixel(TileID(129), 3, 5), bank.tiles.get_pixel(TileID(1), 3, 5));
+    assert_eq!(bank.tiles.get_16(TileID(255)), bank.tiles.get_16(TileID(127)));
+    let mut map = Tilemap::<4>::new(2, 2);
+    map.set_cell(0, 0, Cell::new(200, 0, 0));
+    let video = new_video();
+    render(&video, &[&bank], &[&map]);
+}
+
+#[test]
+fn test_tile_mode_mismatch() {
+    let mut bank = Bank::new_16_colors();
+    assert!(bank.append_tile(&Tile::default()).is_err());
+    assert!(bank.append_tiles(&[Tile::default()]).is_err());
+    assert!(bank.append(&test_bank()).is_err());
+    // The source colors aren't left behind either
+    assert_eq!(bank.colors.color_count(), 0);
+
+    let mut bank = Bank::new();
+    assert!(bank.append_tile_16(&Tile::default()).is_err());
+    assert!(bank.append_tiles_16(&[Tile::default()]).is_err());
+
+    // Same when the tile animations don't fit
+    for i in 0..TILE_ANIM_COUNT {
+        bank.tiles.set_anim(TileAnim::new(TileID(i as u8), 4, &[TileID(0)]));
+    }
+    let mut source = test_bank();
+    source.tiles.set_anim(TileAnim::new(TILE_ARROW, 4, &[TILE_ARROW, TILE_CHECKER]));
+    assert_eq!(bank.append(&source).err(), Some("Not enough space in bank for tile animations"));
+    assert_eq!(bank.colors.color_count(), 0);
+}
+
+#[test]
+fn test_bg_16_colors() {
+    let mut video = new_video();
+    let bank = bank_16();
+    let mut map = Tilemap::<100>::new(10, 8);
+    for row in 0..map.rows as i16 {
+        for col in 0..map.columns as i16 {
+            let index = (row * map.columns as i16 + col) as usize;
+            let flags =
+                TileFlags::default().with_transform(index & 1 != 0, index & 2 != 0, index & 4 != 0);
+            // Palette is ignored in 16 color mode
+            map.set_cell(col, row, Cell { id: TileID(1), flags, colors: Palette(0) });
+        }
+    }
+    video.draw_fg_tile(DrawBundle {
+        x: 20,
+        y: 12,
+        id: TileID(1),
+        flags: TileFlags::default().with_rotation(true),
+        colors: Palette(0),
+        size: SpriteSize::Normal,
+    });
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("bg_16_colors", &video, &frame);
+}
+
+#[test]
+fn test_tile_anim_table() {
+    let mut bank = test_bank();
+    let anim = TileAnim::new(TILE_ARROW, 30, &[TILE_ARROW, TILE_CHECKER, TILE_BLOCK]);
+    bank.tiles.set_anim(anim);
+    bank.tiles.set_anim(TileAnim::new(TILE_BLOCK, 60, &[TILE_BLOCK, TILE_EMPTY]));
+    assert_eq!(bank.tiles.anims().len(), 2);
+
+    // 60 fps video, 30 fps animation: each frame lasts 2 video frames
+    let frames: std::vec::Vec<_> =
+        (0..8).map(|frame| bank.tiles.resolve_anim(TILE_ARROW, frame, 60)).collect();
+    assert_eq!(frames, [1, 1, 2, 2, 3, 3, 1, 1].map(TileID));
+    assert_eq!(bank.tiles.resolve_anim(TILE_CHECKER, 2, 60), TILE_CHECKER);
+
+    // Animations follow their tiles when appended to another bank
+    let mut combined = test_bank();
+    let offset = combined.append_tiles_from_bank(&bank, None).unwrap();
+    assert_eq!(offset, 4);
+    assert_eq!(combined.tiles.resolve_anim(TileID(4 + 1), 2, 60), TileID(4 + 2));
+    assert_eq!(combined.tiles.resolve_anim(TileID(4 + 3), 1, 60), TileID(4));
+
+    assert_eq!(bank.tiles.remove_anim(TILE_ARROW), Some(anim));
+    assert_eq!(bank.tiles.remove_anim(TILE_ARROW), None);
+    assert_eq!(bank.tiles.resolve_anim(TILE_ARROW, 2, 60), TILE_ARROW);
+    assert_eq!(bank.tiles.resolve_anim(TILE_BLOCK, 1, 60), TILE_EMPTY);
+
+    let from_const = TileBank::from_tiles(&[]).with_anims(&[anim]);
+    assert_eq!(from_const.anims(), [anim]);
+}
+
+#[test]
+fn test_tile_anim_render() {
+    let mut video = new_video();
+    let mut bank = test_bank();
+    let map = test_tilemap();
+    bank.tiles.set_anim(TileAnim::new(TILE_ARROW, 30, &[TILE_ARROW, TILE_CHECKER, TILE_BLOCK]));
+
+    let mut expected_bank = test_bank();
+    for (frame_number, tile) in
+        [(0, TILE_ARROW), (2, TILE_CHECKER), (5, TILE_BLOCK), (6, TILE_ARROW)]
+    {
+        video.frame_number = frame_number;
+        // Same as rendering the map with the arrow tile replaced
+        expected_bank.tiles.tiles[TILE_ARROW.0 as usize] = bank.tiles.tiles[tile.0 as usize];
+        let expected = render(&video, &[&expected_bank], &[&map]);
+        assert!(render(&video, &[&bank], &[&map]) == expected, "Mismatch on frame {frame_number}");
+    }
+}
+
+#[test]
+fn test_bank_append_remaps_colors() {
+    let colors_a = [RGBA12::TRANSPARENT, RGBA12::RED, RGBA12::GREEN, RGBA12::BLUE];
+    let colors_b = [RGBA12::TRANSPARENT, RGBA12::BLUE, RGBA12::YELLOW, RGBA12::RED];
+    let mut bank_a = Bank::new();
+    bank_a.colors = ColorBank::new_from(&colors_a);
+    bank_a.append_tile(&tile_from_rows(["01230123"; 8])).unwrap();
+    let mut bank_b = test_bank();
+    bank_b.colors = ColorBank::new_from(&colors_b);
+
+    let mut merged = Bank::new();
+    let remap_a = merged.append(&bank_a).unwrap();
+    let remap_b = merged.append(&bank_b).unwrap();
+    assert_eq!(remap_a.tile_offset, 0);
+    assert_eq!(remap_b.tile_offset, 1);
+    assert_eq!(remap_b.colors[..4], [0, 3, 4, 1]);
+    assert_eq!(merged.colors.color_count(), 5);
+
+    // Remapped cells display the same colors they had in the source bank
+    let cell = Cell {
+        id: TILE_ARROW,
+        flags: TileFlags::default(),
+        colors: Palette::new(0, 1, 2, 3),
+    };
+    let merged_cell = remap_b.cell(cell).unwrap();
+    assert!(remap_b.cell(cell.with_id(255)).is_err());
+    assert_eq!(merged_cell.id, TileID(TILE_ARROW.0 + 1));
+    for slot in 0..4 {
+        let color = merged.colors.palette[merged_cell.colors.get(slot) as usize];
+        assert_eq!(color, bank_b.colors.palette[cell.colors.get(slot) as usize]);
+    }
+    let mut map = Tilemap::<4>::new(2, 2);
+    map.set_cell(1, 1, cell);
+    map.remap_colors(&remap_b.colors);
+    assert_eq!(map.get_cell(1, 1).unwrap().colors, merged_cell.colors);
+
+    // 16 color tiles index the bank colors directly, so their pixels are remapped
+    let mut merged_16 = Bank::new_16_colors();
+    merged_16.colors = ColorBank::new_from(&[RGBA12::TRANSPARENT, RGBA12::PINK]);
+    let source_16 = bank_16();
+    let remap = merged_16.append(&source_16).unwrap();
+    let id = TileID(remap.tile_offset + 1);
+    for y in 0..TILE_SIZE {
+        for x in 0..TILE_SIZE {
+            let color = merged_16.colors.palette[merged_16.tiles.get_pixel(id, x, y) as usize];
+            let expected =
+                source_16.colors.palette[source_16.tiles.get_pixel(TileID(1), x, y) as usize];
+            assert_eq!(color, expected);
+        }
+    }
+}
+
+/// Returns how "tile" looks when displayed with "flags".
+fn transformed(tile: &Tile<2>, flags: TileFlags) -> Tile<2> {
+    let mut result = Tile::<2>::default();
+    for y in 0..TILE_SIZE {
+        for x in 0..TILE_SIZE {
+            let (tx, ty) = flags.transform_coords(x, y, TILE_SIZE);
+            result.set_pixel(x, y, tile.get_pixel(tx, ty));
+        }
+    }
+    result
+}
+
+#[test]
+fn test_compose_transforms() {
+    let arrow = test_bank().tiles.tiles[TILE_ARROW.0 as usize];
+    let all_flags =
+        || (0..8).map(|i| TileFlags::default().with_transform(i & 1 != 0, i & 2 != 0, i & 4 != 0));
+    for inner in all_flags() {
+        for outer in all_flags() {
+            let expected = transformed(&transformed(&arrow, inner), outer);
+            let composed = compose_transforms(outer, inner);
+            assert_eq!(transformed(&arrow, composed), expected, "{outer:?} after {inner:?}");
+        }
+    }
+}
+
+#[test]
+fn test_bank_append_dedup() {
+    let arrow = test_bank().tiles.tiles[TILE_ARROW.0 as usize];
+    let flip_x = TileFlags::default().with_flip_x(true);
+    let rotated = TileFlags::default().with_rotation(true);
+
+    let mut bank = test_bank();
+    let new_tile = tile_from_rows(["01010101"; 8]);
+    let remap = bank
+        .append_tiles_dedup(&[transformed(&arrow, flip_x), new_tile, arrow, new_tile], true)
+        .unwrap();
+    assert_eq!(bank.tiles.count(), 5);
+    assert_eq!(remap.len(), 4);
+    assert_eq!(remap.get(TileID(0)), Some((TILE_ARROW, flip_x)));
+    assert_eq!(remap.get(TileID(1)), Some((TileID(4), TileFlags::default())));
+    assert_eq!(remap.get(TileID(2)), Some((TILE_ARROW, TileFlags::default())));
+    assert_eq!(remap.get(TileID(3)), Some((TileID(4), TileFlags::default())));
+
+    // Without transforms, only identical tiles are merged
+    let remap = bank.append_tiles_dedup(&[transformed(&arrow, flip_x)], false).unwrap();
+    assert_eq!(remap.get(TileID(0)), Some((TileID(5), TileFlags::default())));
+
+    // A bank made of transformed copies of the test tiles needs no new tiles, and its
+    // remapped tilemap renders exactly the same
+    let mut source = Bank::new();
+    source.colors.load_default();
+    source.append_tile(&Tile::default()).unwrap();
+    source.append_tile(&transformed(&arrow, rotated)).unwrap();
+    source.append_tile(&transformed(&arrow, flip_x.with_flip_y(true))).unwrap();
+    source.append_tile(&test_bank().tiles.tiles[TILE_BLOCK.0 as usize]).unwrap();
+    let mut map = test_tilemap();
+    for (i, cell) in map.cells.iter_mut().enumerate() {
+        cell.id = TileID(i as u8 % 4);
+    }
+
+    let video = new_video();
+    let expected = render(&video, &[&source], &[&map]);
+
+    let mut merged = test_bank();
+    let remap = merged.append_dedup(&source, true).unwrap();
+    assert_eq!(merged.tiles.count(), 4);
+
+    // Cells using tiles that weren't in the source bank are rejected, and nothing is patched
+    let mut invalid = test_tilemap();
+    invalid.cells[3].id = TileID(4);
+    let cells = invalid.cells;
+    assert!(remap.cell(invalid.cells[3]).is_err());
+    assert_eq!(remap.apply(&mut invalid), Err("Remapped tile ID is out of range"));
+    assert_eq!(invalid.cells, cells);
+
+    remap.apply(&mut map).unwrap();
+    assert!(render(&video, &[&merged], &[&map]) == expected);
+}
+
+#[test]
+fn test_append_dedup_skips_animated_tiles() {
+    let arrow = test_bank().tiles.tiles[TILE_ARROW.0 as usize];
+    let block = test_bank().tiles.tiles[TILE_BLOCK.0 as usize];
+    let new_tile = tile_from_rows(["01010101"; 8]);
+
+    let mut bank = test_bank();
+    bank.tiles.set_anim(TileAnim::new(TILE_ARROW, 4, &[TILE_ARROW, TILE_CHECKER]));
+    let mut source = Bank::new();
+    source.colors.load_default();
+    for tile in [Tile::default(), arrow, new_tile, new_tile, block] {
+        source.append_tile(&tile).unwrap();
+    }
+    source.tiles.set_anim(TileAnim::new(TileID(2), 4, &[TileID(2), TileID(4)]));
+
+    let remap = bank.append_dedup(&source, true).unwrap();
+    let ids: [TileID; 5] = core::array::from_fn(|i| remap.get(TileID(i as u8)).unwrap().0);
+    // The arrow is animated in the bank, the new tile is animated in the source (so its
+    // static copy can't share it), and the block is a source animation frame.
+    assert_eq!(ids, [TILE_EMPTY, TileID(4), TileID(5), TileID(6), TileID(7)]);
+    assert_eq!(bank.tiles.anims()[1].tile, TileID(5));
+    assert_eq!(&bank.tiles.anims()[1].frames[..2], &[TileID(5), TileID(7)]);
+
+    // A failure rolls back colors as well as tiles
+    let mut bank = test_bank();
+    bank.colors.restore_state(12);
+    bank.tiles.restore_state(TILE_COUNT as u16 - 1);
+    let mut source = Bank::new();
+    source.colors.push_color(RGBA12::new(1, 2, 3));
+    source.colors.push_color(RGBA12::new(3, 2, 1));
+    source.append_tile(&arrow).unwrap();
+    source.append_tile(&new_tile).unwrap();
+    source.tiles.set_anim(TileAnim::new(TileID(0), 4, &[TileID(0), TileID(1)]));
+    assert!(bank.append_dedup(&source, true).is_err());
+    assert_eq!(bank.colors.palette_head, 12);
+    assert_eq!(bank.tiles.count(), TILE_COUNT - 1);
+    assert!(bank.tiles.anims().is_empty());
+}
@@ -0,0 +1,78 @@
+use super::snapshot::*;
+use super::*;
+
+fn draw_blocks(video: &mut VideoChip) {
+    for i in 0..5i16 {
+        video.draw_fg_tile(DrawBundle {
+            x: 2 + (i * 13),
+            y: 20,
+            id: TILE_BLOCK,
+            flags: TileFlags::default(),
+            colors: Palette::new(0, 15, 8, 3),
+            size: SpriteSize::Normal,
+        });
+    }
+}
+
+#[test]
+fn test_window_spans() {
+    let mut window = Window::new();
+    window.set_rect(4, 2, 10, 3);
+    assert_eq!(window.lines[1], WindowSpan::default());
+    assert_eq!(window.lines[2], WindowSpan { left: 4, right: 14 });
+    assert_eq!(window.lines[4], WindowSpan { left: 4, right: 14 });
+    assert_eq!(window.lines[5], WindowSpan::default());
+
+    let spans = [WindowSpan { left: 1, right: 2 }; 4];
+    window.set_lines(MAX_RESOLUTION_Y as u16 - 2, &spans);
+    assert_eq!(window.lines[MAX_RESOLUTION_Y - 1], spans[0]);
+    window.set_span(MAX_RESOLUTION_Y as u16, 0, 10); // Ignored
+}
+
+#[test]
+fn test_window_disabled() {
+    // A configured but disabled window changes nothing
+    let mut video = new_video();
+    draw_blocks(&mut video);
+    let bank = test_bank();
+    let map = test_tilemap();
+    let expected = render(&video, &[&bank], &[&map]);
+    video.windows[0].set_rect(10, 10, 20, 20);
+    video.windows[0].bg = WindowMask::Inside;
+    video.windows[0].sprites = WindowMask::Inside;
+    assert_eq!(render(&video, &[&bank], &[&map]), expected);
+}
+
+#[test]
+fn test_windows() {
+    let mut video = new_video();
+    draw_blocks(&mut video);
+    video.color_math.brightness = -160;
+
+    // Iris: a diamond shaped window, BG and sprites are hidden outside of it
+    let iris = &mut video.windows[0];
+    iris.enabled = true;
+    iris.bg = WindowMask::Outside;
+    iris.sprites = WindowMask::Outside;
+    iris.color_math = WindowMask::Inside;
+    for y in 0..SCREEN_H {
+        let half_width = 20u16.saturating_sub(y.abs_diff(SCREEN_H / 2));
+        let center = SCREEN_W / 2;
+        iris.set_span(y, center - half_width, center + half_width);
+    }
+
+    // HUD cutout: the line IRQ opens a strip where sprites are hidden
+    let hud = &mut video.windows[1];
+    hud.enabled = true;
+    hud.sprites = WindowMask::Inside;
+    video.irq_line = Some(|iter, _video, _map| {
+        let open = iter.y() >= 22 && iter.y() < 26;
+        iter.window_spans[1] =
+            if open { WindowSpan { left: 0, right: 64 } } else { WindowSpan::default() };
+    });
+
+    let bank = test_bank();
+    let map = test_tilemap();
+    let frame = render(&video, &[&bank], &[&map]);
+    assert_snapshot("windows", &video, &frame);
+}
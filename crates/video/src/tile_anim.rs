@@ -0,0 +1,40 @@
+use crate::*;
+
+/// A sequence of tiles that replaces a single tile ID in every BG cell that uses it,
+/// advancing automatically with the VideoChip's frame counter. Tile animations always loop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileAnim {
+    /// The tile ID used in the tilemaps. Usually the first frame.
+    pub tile: TileID,
+    /// Playback speed in frames per second.
+    pub fps: u8,
+    pub len: u8,
+    pub frames: [TileID; TILE_ANIM_FRAMES],
+}
+
+impl TileAnim {
+    pub const fn new(tile: TileID, fps: u8, frames: &[TileID]) -> Self {
+        assert!(!frames.is_empty(), "TileAnim: frames can't be empty");
+        assert!(frames.len() <= TILE_ANIM_FRAMES, "TileAnim: too many frames");
+        let mut result = Self {
+            tile,
+            fps,
+            len: frames.len() as u8,
+            frames: [tile; TILE_ANIM_FRAMES],
+        };
+        let mut i = 0;
+        while i < frames.len() {
+            result.frames[i] = frames[i];
+            i += 1;
+        }
+        result
+    }
+
+    /// The tile displayed on a given frame, using the same timing as sprite animations.
+    #[inline]
+    pub fn frame(&self, frame_number: usize, frame_rate: u8) -> TileID {
+        let frame_duration = (frame_rate as usize / self.fps.max(1) as usize).max(1);
+        let index = (frame_number / frame_duration) % self.len.max(1) as usize;
+        self.frames[index]
+    }
+}
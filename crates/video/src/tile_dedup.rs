@@ -0,0 +1,288 @@
+use crate::*;
+
+/// Every flip and rotation combination, identity first.
+const TRANSFORMS: [(bool, bool, bool); 8] = [
+    (false, false, false),
+    (true, false, false),
+    (false, true, false),
+    (true, true, false),
+    (false, false, true),
+    (true, false, true),
+    (false, true, true),
+    (true, true, true),
+];
+
+/// Returns the transform that has the same effect as displaying with "outer",
+/// a tile that was already transformed by "inner". Other flags are taken from "outer".
+pub fn compose_transforms(outer: TileFlags, inner: TileFlags) -> TileFlags {
+    let high = TILE_SIZE - 1;
+    let composed = |x, y| {
+        let (x, y) = outer.transform_coords(x, y, TILE_SIZE);
+        inner.transform_coords(x, y, TILE_SIZE)
+    };
+    // Transforms are affine, so three corners are enough to tell them apart
+    TRANSFORMS
+        .iter()
+        .map(|&(flip_x, flip_y, rotation)| outer.with_transform(flip_x, flip_y, rotation))
+        .find(|candidate| {
+            [(0, 0), (high, 0), (0, high)]
+                .iter()
+                .all(|&(x, y)| candidate.transform_coords(x, y, TILE_SIZE) == composed(x, y))
+        })
+        .unwrap_or(outer)
+}
+
+/// Maps the tiles of a source bank to the tiles that replace them in a destination bank,
+/// plus the flip and rotation needed to display them the same way.
+#[derive(Debug, Clone)]
+pub struct TileRemap {
+    entries: [(TileID, TileFlags); TILE_COUNT],
+    len: u16,
+    /// Maps source bank colors to destination bank colors.
+    pub colors: PaletteRemap,
+}
+
+impl TileRemap {
+    pub(crate) const fn new() -> Self {
+        Self {
+            entries: [(TileID(0), TileFlags(0)); TILE_COUNT],
+            len: 0,
+            colors: DEFAULT_MAPPING,
+        }
+    }
+
+    pub(crate) fn push(&mut self, id: TileID, flags: TileFlags) {
+        self.entries[self.len as usize] = (id, flags);
+        self.len += 1;
+    }
+
+    /// Number of source tiles in the table.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// New TileID and transform for a source tile.
+    pub fn get(&self, id: TileID) -> Option<(TileID, TileFlags)> {
+        self.entries[..self.len as usize].get(id.0 as usize).copied()
+    }
+
+    /// Converts a cell that referenced the source bank into one that references the
+    /// destination, combining the cell's own transform with the deduplicated tile's.
+    /// Fails if the cell's tile ID is not in the table, which means the cell wasn't
+    /// using a tile from the source bank.
+    pub fn cell(&self, cell: Cell) -> Result<Cell, &'static str> {
+        let (id, flags) = self.get(cell.id).ok_or("Remapped tile ID is out of range")?;
+        Ok(Cell {
+            id,
+            flags: compose_transforms(cell.flags, flags),
+            colors: cell.colors.remap(&self.colors),
+        })
+    }
+
+    /// Patches every cell within the active dimensions of a tilemap that came with the
+    /// source bank. On error the tilemap is left unchanged.
+    pub fn apply<const LEN: usize>(&self, map: &mut Tilemap<LEN>) -> Result<(), &'static str> {
+        let len = map.columns as usize * map.rows as usize;
+        let cells = &mut map.cells[..len];
+        // Checked up front, so that a failure doesn't leave the map half patched
+        if cells.iter().any(|cell| self.get(cell.id).is_none()) {
+            return Err("Remapped tile ID is out of range");
+        }
+        for cell in cells {
+            *cell = self.cell(*cell)?;
+        }
+        Ok(())
+    }
+}
+
+impl TileBank {
+    /// Looks for an existing tile with the same pixels as "pixel(x, y)", optionally flipped or
+    /// rotated. Returns its ID and the transform that makes it match. Animated tiles, and any
+    /// tile for which "skip" returns true, are never matched.
+    fn find_pixels(
+        &self,
+        pixel: impl Fn(u8, u8) -> u8,
+        allow_transforms: bool,
+        skip: impl Fn(TileID) -> bool,
+    ) -> Option<(TileID, TileFlags)> {
+        let transforms = if allow_transforms { &TRANSFORMS[..] } else { &TRANSFORMS[..1] };
+        for &(flip_x, flip_y, rotation) in transforms {
+            let flags = TileFlags::default().with_transform(flip_x, flip_y, rotation);
+            for index in 0..self.count() {
+                let id = TileID(index as u8);
+                if self.anim_lookup[index] != 0 || skip(id) {
+                    continue;
+                }
+                let matches = (0..TILE_SIZE).all(|y| {
+                    (0..TILE_SIZE).all(|x| {
+                        let (tx, ty) = flags.transform_coords(x, y, TILE_SIZE);
+                        self.get_pixel(id, tx, ty) == pixel(x, y)
+                    })
+                });
+                if matches {
+                    return Some((id, flags));
+                }
+            }
+        }
+        None
+    }
+
+    /// Looks for an existing tile that displays exactly like "tile" when using the returned flags.
+    /// Animated tiles are skipped, since they don't always display the same pixels.
+    pub fn find(&self, tile: &Tile<2>, allow_transforms: bool) -> Option<(TileID, TileFlags)> {
+        if self.mode != TileMode::Colors4 {
+            return None;
+        }
+        self.find_pixels(|x, y| tile.get_pixel(x, y), allow_transforms, |_| false)
+    }
+
+    /// Same as [TileBank::find], for 16 color tiles.
+    pub fn find_16(&self, tile: &Tile<4>, allow_transforms: bool) -> Option<(TileID, TileFlags)> {
+        if self.mode != TileMode::Colors16 {
+            return None;
+        }
+        self.find_pixels(|x, y| tile.get_pixel(x, y), allow_transforms, |_| false)
+    }
+}
+
+impl Bank {
+    /// Appends tiles, reusing existing ones (including flipped and rotated variants, if
+    /// "allow_transforms" is true). Returns a table used to patch the source tilemap cells.
+    /// On error the bank is left unchanged.
+    pub fn append_tiles_dedup(
+        &mut self,
+        source: &[Tile<2>],
+        allow_transforms: bool,
+    ) -> Result<TileRemap, &'static str> {
+        if self.tiles.mode != TileMode::Colors4 {
+            return Err("Can't append 4 color tiles to a 16 color bank");
+        }
+        if source.len() > TILE_COUNT {
+            return Err("Source has more tiles than a bank can hold");
+        }
+        let head = self.tiles.head;
+        let mut remap = TileRemap::new();
+        for tile in source {
+            let (id, flags) = match self.tiles.find(tile, allow_transforms) {
+                Some(found) => found,
+                None if self.tiles.count() < self.tiles.capacity() => {
+                    (self.tiles.add(tile), TileFlags::default())
+                },
+                None => {
+                    self.tiles.restore_state(head);
+                    return Err("Not enough space in bank for tiles");
+                },
+            };
+            remap.push(id, flags);
+        }
+        Ok(remap)
+    }
+
+    /// Appends another bank's colors and tiles, reusing existing colors and tiles.
+    /// Tiles used by tile animations (on either side) are never merged, since their pixels
+    /// change over time and animation frames can't carry a transform.
+    /// On error the bank is left unchanged.
+    pub fn append_dedup(
+        &mut self,
+        source: &Bank,
+        allow_transforms: bool,
+    ) -> Result<TileRemap, &'static str> {
+        if source.tiles.mode != self.tiles.mode {
+            return Err("Can't append tiles from a bank with a different tile mode");
+        }
+        let source_anims = source.tiles.anims();
+        if self.tiles.anims().len() + source_anims.len() > TILE_ANIM_COUNT {
+            return Err("Not enough space in bank for tile animations");
+        }
+        let src_len = source.colors.palette_head as usize;
+        let color_head = self.colors.palette_head;
+        let colors = match self.colors.append(&source.colors.palette[..src_len]) {
+            Ok(colors) => colors,
+            Err(err) => {
+                self.colors.restore_state(color_head);
+                return Err(err);
+            },
+        };
+
+        let head = self.tiles.head;
+        let mut remap = TileRemap::new();
+        remap.colors = colors;
+        // Tiles appended for animated source tiles, which can't be shared either
+        let mut reserved = [false; TILE_COUNT];
+        for index in 0..source.tiles.count() {
+            let id = TileID(index as u8);
+            let animated = source_anims
+                .iter()
+                .any(|anim| anim.tile == id || anim.frames[..anim.len as usize].contains(&id));
+            let found = match self.tiles.mode {
+                TileMode::Colors4 => {
+                    let tile = source.tiles.tiles[index];
+                    let found = if animated {
+                        None
+                    } else {
+                        let pixel = |x, y| tile.get_pixel(x, y);
+                        self.tiles
+                            .find_pixels(pixel, allow_transforms, |id| reserved[id.0 as usize])
+                    };
+                    found.map(Ok).unwrap_or_else(|| {
+                        if self.tiles.count() < self.tiles.capacity() {
+                            Ok((self.tiles.add(&tile), TileFlags::default()))
+                        } else {
+                            Err("Not enough space in bank for tiles")
+                        }
+                    })
+                },
+                TileMode::Colors16 => {
+                    // Pixels index the bank colors directly, so they're remapped before comparing
+                    let mut tile = source.tiles.get_16(id);
+                    for y in 0..TILE_SIZE {
+                        for x in 0..TILE_SIZE {
+                            tile.set_pixel(x, y, colors[tile.get_pixel(x, y) as usize]);
+                        }
+                    }
+                    let found = if animated {
+                        None
+                    } else {
+                        let pixel = |x, y| tile.get_pixel(x, y);
+                        self.tiles
+                            .find_pixels(pixel, allow_transforms, |id| reserved[id.0 as usize])
+                    };
+                    found.map(Ok).unwrap_or_else(|| {
+                        if self.tiles.count() < self.tiles.capacity() {
+                            Ok((self.tiles.add_16(&tile), TileFlags::default()))
+                        } else {
+                            Err("Not enough space in bank for tiles")
+                        }
+                    })
+                },
+            };
+            match found {
+                Ok((id, flags)) => {
+                    if animated {
+                        reserved[id.0 as usize] = true;
+                    }
+                    remap.push(id, flags);
+                },
+                Err(err) => {
+                    self.tiles.restore_state(head);
+                    self.colors.restore_state(color_head);
+                    return Err(err);
+                },
+            }
+        }
+
+        for anim in source_anims {
+            let mut anim = *anim;
+            anim.tile = remap.get(anim.tile).map_or(anim.tile, |(id, _)| id);
+            for frame in &mut anim.frames {
+                *frame = remap.get(*frame).map_or(*frame, |(id, _)| id);
+            }
+            self.tiles.set_anim(anim);
+        }
+        Ok(remap)
+    }
+}
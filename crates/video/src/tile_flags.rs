@@ -6,6 +6,7 @@ const MASK_FG: u8 = 0b_0001_0000;
 const MASK_COLLIDER: u8 = 0b_0000_1000;
 const MASK_TRIGGER: u8 = 0b_0000_0100;
 const MASK_INVISIBLE: u8 = 0b_0000_0010;
+const MASK_BEHIND_BG: u8 = 0b_0000_0001;
 
 /// A single byte struct that stores a tile's render state such as
 /// horizontal flip, vertical flip, rotation and custom data.
@@ -53,6 +54,11 @@ impl TileFlags {
         if state { Self(self.0 | MASK_FG) } else { Self(self.0 & !MASK_FG) }
     }
 
+    /// Consumes the original flag and ensures a sprite is rendered behind BG tiles.
+    pub const fn with_behind_bg(self, state:bool) -> Self {
+        if state { Self(self.0 | MASK_BEHIND_BG) } else { Self(self.0 & !MASK_BEHIND_BG) }
+    }
+
     /// Consumes the original flag and ensures tile is a collider.
     pub const fn with_collision(self, state:bool) -> Self {
         if state { Self(self.0 | MASK_COLLIDER) } else { Self(self.0 & !MASK_COLLIDER) }
@@ -96,6 +102,12 @@ impl TileFlags {
         if state { self.0 |= MASK_FG } else { self.0 &= !MASK_FG }
     }
 
+    /// If true and this is a sprite, it will be rendered behind opaque BG pixels,
+    /// but still in front of the BG color. This value is ignored when used on BG tiles.
+    pub fn set_behind_bg(&mut self, state: bool) {
+        if state { self.0 |= MASK_BEHIND_BG } else { self.0 &= !MASK_BEHIND_BG }
+    }
+
     /// If true the tile will be a collider.
     pub fn set_collision(&mut self, state: bool) {
         if state { self.0 |= MASK_COLLIDER } else { self.0 &= !MASK_COLLIDER }
@@ -152,6 +164,12 @@ impl TileFlags {
         self.0 & MASK_FG != 0
     }
 
+    /// If true and this is a sprite, it will be rendered behind opaque BG pixels,
+    /// but still in front of the BG color. This value is ignored when used on BG tiles.
+    pub const fn is_behind_bg(&self) -> bool {
+        self.0 & MASK_BEHIND_BG != 0
+    }
+
     /// If true this tile is a collision tile.
     pub const fn is_collider(&self) -> bool {
         self.0 & MASK_COLLIDER != 0
@@ -187,6 +205,11 @@ impl TileFlags {
         Self(self.0 ^ MASK_FG)
     }
 
+    /// Toggles the behind BG state (sprites only)
+    pub const fn toggle_behind_bg(self) -> Self {
+        Self(self.0 ^ MASK_BEHIND_BG)
+    }
+
     /// Transform screen coordinates to tile coordinates based on flip/rotation flags.
     /// This is the canonical transformation used by the renderer.
     /// Given a screen position (x, y), returns which tile pixel (tx, ty) to read.
@@ -101,6 +101,14 @@ impl<const CELL_COUNT: usize> Tilemap<CELL_COUNT> {
         }
     }
 
+    /// Replaces the bank colors used by every cell palette, i.e. after appending
+    /// the tilemap's bank to a different bank. See [Bank::append].
+    pub fn remap_colors(&mut self, remap: &PaletteRemap) {
+        for cell in &mut self.cells {
+            cell.colors = cell.colors.remap(remap);
+        }
+    }
+
     /// Applies a BgOp operation. Does nothing if out of bounds.
     pub fn set_op(&mut self, op: BgOp) {
         if let Some(index) = self.get_index(op.col, op.row) {
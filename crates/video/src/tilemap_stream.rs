@@ -0,0 +1,170 @@
+use crate::*;
+use tato_math::Vec2;
+
+/// Provides the cells of a world that is too large (or too expensive) to keep in a Tilemap.
+/// Coordinates are in world cells, and may be outside the world's bounds.
+pub trait CellSource {
+    fn cell(&mut self, col: i16, row: i16) -> Cell;
+}
+
+/// Any closure can act as a source, i.e. to decompress level data on demand.
+impl<F> CellSource for F
+where
+    F: FnMut(i16, i16) -> Cell,
+{
+    fn cell(&mut self, col: i16, row: i16) -> Cell {
+        self(col, row)
+    }
+}
+
+/// Cells outside the map are invisible.
+impl CellSource for TilemapRef<'_> {
+    fn cell(&mut self, col: i16, row: i16) -> Cell {
+        self.get_cell(col, row).unwrap_or_else(|| {
+            let mut cell = Cell::default();
+            cell.flags.set_invisible(true);
+            cell
+        })
+    }
+}
+
+/// Cells outside the map are invisible.
+impl<const LEN: usize> CellSource for Tilemap<LEN> {
+    fn cell(&mut self, col: i16, row: i16) -> Cell {
+        self.as_ref().cell(col, row)
+    }
+}
+
+/// A small tilemap used as a ring buffer over a much larger world. Every world cell is
+/// stored at (col % columns, row % rows), so when the buffer is rendered with BG wrapping
+/// enabled the regular scroll value maps straight into it: the VideoChip scroll is simply
+/// the world scroll, and only the rows and columns that scroll into view need to be pulled
+/// from the source.
+///
+/// The buffer needs to be at least one tile larger than the viewport on each axis,
+/// i.e. [StreamingTilemap::for_viewport].
+///
+/// Limitations:
+/// - The renderer knows nothing about the ring buffer. The mapping only works because of
+///   BG wrapping, so "VideoChip::wrap_bg" must be enabled ([StreamingTilemap::follow]
+///   fails otherwise).
+/// - The world scroll is the VideoChip scroll, so worlds are limited to the i16 range
+///   (32767 pixels, or 4095 tiles in each direction).
+#[derive(Debug, Clone)]
+pub struct StreamingTilemap<const CELL_COUNT: usize> {
+    pub map: Tilemap<CELL_COUNT>,
+    /// World coordinates (in cells) of the top-left cell currently loaded, if any.
+    origin: Option<Vec2<i16>>,
+}
+
+impl<const CELL_COUNT: usize> StreamingTilemap<CELL_COUNT> {
+    /// Creates a buffer with the specified dimensions. Nothing is loaded until the first update.
+    pub fn new(columns: u16, rows: u16) -> Self {
+        Self { map: Tilemap::new(columns, rows), origin: None }
+    }
+
+    /// Creates the smallest buffer that covers a viewport of the given size in pixels,
+    /// at any scroll value.
+    pub fn for_viewport(width: u16, height: u16) -> Self {
+        let tile = TILE_SIZE as u16;
+        Self::new(width.div_ceil(tile) + 1, height.div_ceil(tile) + 1)
+    }
+
+    pub fn as_ref(&self) -> TilemapRef<'_> {
+        self.map.as_ref()
+    }
+
+    /// World coordinates (in cells) of the top-left cell currently in the buffer.
+    pub fn origin(&self) -> Option<Vec2<i16>> {
+        self.origin
+    }
+
+    /// Forces the next update to load the entire buffer again, i.e. after the world changes.
+    pub fn invalidate(&mut self) {
+        self.origin = None;
+    }
+
+    /// Same as [StreamingTilemap::update], using the VideoChip's scroll.
+    /// Fails without loading anything if "VideoChip::wrap_bg" is disabled, since the
+    /// buffer only renders correctly with BG wrapping.
+    pub fn follow(
+        &mut self,
+        video: &VideoChip,
+        source: &mut impl CellSource,
+    ) -> Result<usize, &'static str> {
+        if !video.wrap_bg {
+            return Err("Streaming tilemaps require VideoChip::wrap_bg");
+        }
+        Ok(self.update(video.scroll, source))
+    }
+
+    /// Pulls any cells that became visible since the last update from the source.
+    /// "scroll" is the world position, in pixels, of the top-left corner of the viewport.
+    /// Returns how many cells were loaded.
+    pub fn update(&mut self, scroll: Vec2<i16>, source: &mut impl CellSource) -> usize {
+        let tile = TILE_SIZE as i16;
+        let columns = self.map.columns as i16;
+        let rows = self.map.rows as i16;
+        let new = Vec2 { x: scroll.x.div_euclid(tile), y: scroll.y.div_euclid(tile) };
+
+        let Some(old) = self.origin else {
+            self.origin = Some(new);
+            return self.load_rect(new.x, new.y, columns, rows, source);
+        };
+
+        let dx = new.x - old.x;
+        let dy = new.y - old.y;
+        // A big jump (i.e. a level warp) leaves nothing reusable in the buffer.
+        if dx.abs() >= columns || dy.abs() >= rows {
+            self.origin = Some(new);
+            return self.load_rect(new.x, new.y, columns, rows, source);
+        }
+
+        self.origin = Some(new);
+        let mut loaded = 0;
+        // Columns that scrolled into view, along the entire new height.
+        if dx > 0 {
+            loaded += self.load_rect(old.x + columns, new.y, dx, rows, source);
+        } else if dx < 0 {
+            loaded += self.load_rect(new.x, new.y, -dx, rows, source);
+        }
+        // Rows that scrolled into view, skipping the columns already loaded above.
+        let (col, width) = match dx {
+            d if d > 0 => (new.x, columns - d),
+            d if d < 0 => (new.x - d, columns + d),
+            _ => (new.x, columns),
+        };
+        if dy > 0 {
+            loaded += self.load_rect(col, old.y + rows, width, dy, source);
+        } else if dy < 0 {
+            loaded += self.load_rect(col, new.y, width, -dy, source);
+        }
+        loaded
+    }
+
+    /// Copies a rectangle of world cells into their ring buffer positions.
+    fn load_rect(
+        &mut self,
+        col: i16,
+        row: i16,
+        width: i16,
+        height: i16,
+        source: &mut impl CellSource,
+    ) -> usize {
+        let columns = self.map.columns as i16;
+        let rows = self.map.rows as i16;
+        for world_row in row..row + height {
+            for world_col in col..col + width {
+                let cell = source.cell(world_col, world_row);
+                self.map.set_cell(world_col.rem_euclid(columns), world_row.rem_euclid(rows), cell);
+            }
+        }
+        (width.max(0) as usize) * (height.max(0) as usize)
+    }
+}
+
+impl<'a, const CELL_COUNT: usize> From<&'a StreamingTilemap<CELL_COUNT>> for TilemapRef<'a> {
+    fn from(stream: &'a StreamingTilemap<CELL_COUNT>) -> Self {
+        stream.map.as_ref()
+    }
+}
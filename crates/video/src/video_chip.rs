@@ -1,3 +1,4 @@
+use core::cell::Cell;
 use tato_math::Vec2;
 
 use crate::*;
@@ -8,8 +9,12 @@ pub struct DrawBundle {
     pub x: i16,
     pub y: i16,
     pub id: TileID,
+    /// Rotation is ignored by [SpriteSize::Tall] sprites.
     pub flags: TileFlags,
     pub colors: Palette,
+    /// [SpriteSize::Tall] sprites also use the next tile ID, so "id" must not be the last
+    /// tile in the bank.
+    pub size: SpriteSize,
 }
 
 /// A convenient packet of data used to draw a tilemap as a sprite.
@@ -21,6 +26,8 @@ pub struct SpriteBundle {
     pub flip_y: bool,
     pub tile_offset: u8,
     pub palette_override: Option<Palette>,
+    /// Renders the sprite behind opaque BG pixels, but in front of the BG color.
+    pub behind_bg: bool,
 }
 
 /// Main drawing context that manages the screen, tiles, and palette.
@@ -32,23 +39,60 @@ pub struct VideoChip {
     pub crop_color: RGBA12,
     /// Brings sprites "outside the screen" into view.
     pub wrap_sprites: bool,
+    /// Rotates the sprite priority every frame when a scanline has too many sprites,
+    /// so that the excess sprites flicker instead of disappearing.
+    pub sprite_flicker: bool,
     /// Repeats the BG Map outside its borders
     pub wrap_bg: bool,
     /// Offsets the BG Map and Sprite tiles
     pub scroll: Vec2<i16>,
+    /// Samples the main BG map through an affine transform (rotation, scaling) instead
+    /// of rendering it tile by tile. Can be modified per scanline by the line IRQ.
+    pub affine: Option<Affine>,
+    /// Brightness, blending and shadow registers, applied after compositing.
+    pub color_math: ColorMath,
+    /// Block sizes used by the mosaic effect on each layer.
+    pub mosaic: Mosaic,
     ///
     pub frame_rate: u8,
     // pub scroll.x: i16,
     // pub scroll.y: i16,
     // ---------------------- Iterator control ----------------------
+    /// A callback that can modify the iterator in the middle of a line, i.e. for split
+    /// screens or mid-line palette and bank swaps. Only called at "irq_x_trigger".
+    /// Changes carry over to the next line, so the line IRQ usually restores them.
+    pub irq_x: Option<VideoIRQ>,
+    /// Determines where the horizontal IRQ is called on every line.
+    pub irq_x_trigger: IrqTrigger,
     /// A callback that can modify the iterator, called once per line.
     /// It is automatically passed to the PixelIterator.
-    // pub irq_x_callback: Option<VideoIRQ>,
     pub irq_line: Option<VideoIRQ>,
+    /// Called when a sprite collides for the first time in a frame.
+    pub irq_collision: Option<CollisionIRQ>,
+    /// General purpose values for the IRQs, since they're plain functions and can't
+    /// capture any state. Not used by the VideoChip itself.
+    pub irq_context: [i32; IRQ_CONTEXT_LEN],
+    /// Per line scroll, BG color and bank changes, configured as data instead of an IRQ.
+    pub raster: RasterEffects,
+    /// Enables the sprite collision registers. Adds a little overhead to sprite rendering.
+    pub detect_collisions: bool,
     pub fg_tile_bank: u8,
+    /// Bank whose colors are used by sprites, so that sprite tiles can be reused with
+    /// different colors, and sprites don't take up BG colors. If None, sprites use the
+    /// colors of "fg_tile_bank".
+    pub fg_color_bank: Option<u8>,
     pub bg_tile_bank: u8,
+    /// Additional BG layers composited with the main BG map, i.e. for parallax.
+    /// All planes start disabled.
+    pub bg_planes: [BgPlane; BG_PLANE_COUNT],
+    /// Windows that hide layers per scanline. All windows start disabled.
+    pub windows: [Window; WINDOW_COUNT],
+    /// Retained sprites, submitted automatically on every "frame_finish".
+    pub objects: ObjectTable,
     // ---------------------- Main Data ----------------------
     pub(crate) sprite_gen: SpriteGenerator,
+    // Written by the PixelIter while rendering, hence the interior mutability
+    pub(crate) collisions: Cell<SpriteCollisions>,
     pub(crate) w: u16,
     pub(crate) h: u16,
     // ---------------------- Bookkeeping ----------------------
@@ -73,6 +117,7 @@ impl VideoChip {
             bg_color: RGBA12::BLACK,
             crop_color: RGBA12::BLACK,
             wrap_sprites: false,
+            sprite_flicker: false,
             wrap_bg: false,
             frame_rate,
             sprite_gen: SpriteGenerator::new(),
@@ -83,12 +128,25 @@ impl VideoChip {
             w,
             h,
             scroll: Vec2::zero(),
+            affine: None,
+            color_math: ColorMath::default(),
+            mosaic: Mosaic::default(),
             frame_number: 0,
             // Video IRQs
-            // irq_x_callback: None,
+            irq_x: None,
+            irq_x_trigger: IrqTrigger::Off,
             irq_line: None,
+            irq_collision: None,
+            irq_context: [0; IRQ_CONTEXT_LEN],
+            raster: RasterEffects::new(),
+            detect_collisions: false,
+            collisions: Cell::new(SpriteCollisions::default()),
             fg_tile_bank: 0,
+            fg_color_bank: None,
             bg_tile_bank: 0,
+            bg_planes: [BgPlane::default(); BG_PLANE_COUNT],
+            windows: [const { Window::new() }; WINDOW_COUNT],
+            objects: ObjectTable::new(),
         };
         result.reset_all();
 
@@ -135,26 +193,41 @@ impl VideoChip {
     }
 
     /// Does not affect BG or Sprites calculation, but "masks" PixelIter pixels outside
-    /// this rectangular area with the BG Color
+    /// this rectangular area with the BG Color. The area is clipped to the screen.
     pub fn set_viewport(&mut self, left: u16, top: u16, w: u16, h: u16) {
-        self.view_left = left;
-        self.view_top = top;
-        self.view_right = left.saturating_add(w);
-        self.view_bottom = top.saturating_add(h);
+        self.view_left = left.min(self.w);
+        self.view_top = top.min(self.h);
+        self.view_right = left.saturating_add(w).min(self.w);
+        self.view_bottom = top.saturating_add(h).min(self.h);
     }
 
     /// Resets the chip to its initial state.
     pub fn reset_all(&mut self) {
         self.bg_color = RGBA12::BLACK;
         self.wrap_sprites = false;
+        self.sprite_flicker = false;
         self.wrap_bg = false;
         self.frame_number = 0;
         self.fg_tile_bank = 0;
+        self.fg_color_bank = None;
         self.bg_tile_bank = 0;
         self.reset_scroll();
+        self.affine = None;
+        self.color_math = ColorMath::default();
+        self.mosaic = Mosaic::default();
         self.reset_viewport();
         self.reset_sprites();
+        self.reset_bg_planes();
+        self.reset_windows();
+        self.objects.clear();
+        self.irq_x = None;
+        self.irq_x_trigger = IrqTrigger::Off;
         self.irq_line = None;
+        self.irq_collision = None;
+        self.irq_context = [0; IRQ_CONTEXT_LEN];
+        self.raster = RasterEffects::new();
+        self.detect_collisions = false;
+        self.collisions.set(SpriteCollisions::default());
     }
 
     pub fn reset_scroll(&mut self) {
@@ -170,7 +243,45 @@ impl VideoChip {
     }
 
     pub fn reset_sprites(&mut self) {
-        self.sprite_gen.reset();
+        self.sprite_gen.reset(self.sprite_flicker);
+    }
+
+    /// Collision registers from the last rendered frame. Sprite indices follow
+    /// the order in which sprites were drawn in that frame. Empty if "detect_collisions"
+    /// was disabled.
+    pub fn collisions(&self) -> SpriteCollisions {
+        self.collisions.get()
+    }
+
+    /// Sprite statistics for the current frame.
+    pub fn sprite_stats(&self) -> SpriteStats {
+        self.sprite_gen.stats()
+    }
+
+    /// How many sprites were dropped in a scanline because it exceeded SPRITES_PER_LINE.
+    pub fn sprite_overflow(&self, y: u16) -> u8 {
+        self.sprite_gen.scanlines.get(y as usize).map_or(0, |line| line.dropped)
+    }
+
+    /// Iterates the scanlines that dropped sprites this frame, yielding the line
+    /// number and how many sprites were dropped.
+    pub fn overflowed_lines(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+        self.sprite_gen.scanlines[..self.h as usize]
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.dropped > 0)
+            .map(|(y, line)| (y as u16, line.dropped))
+    }
+
+    /// Disables all additional BG planes.
+    pub fn reset_bg_planes(&mut self) {
+        self.bg_planes = [BgPlane::default(); BG_PLANE_COUNT];
+    }
+
+    pub fn reset_windows(&mut self) {
+        for window in &mut self.windows {
+            *window = Window::new();
+        }
     }
 
     /// Flips a coordinate based on the axis length (the length in rows or columns
@@ -199,6 +310,9 @@ impl VideoChip {
                 if bundle.flip_y {
                     flags = flags.toggle_flip_y();
                 };
+                if bundle.behind_bg {
+                    flags = flags.with_behind_bg(true);
+                };
 
                 self.draw_fg_tile(DrawBundle {
                     x: (draw_col as i16 * TILE_SIZE as i16) + bundle.x,
@@ -206,6 +320,7 @@ impl VideoChip {
                     id: cell.id,
                     flags,
                     colors: bundle.palette_override.unwrap_or(cell.colors),
+                    size: SpriteSize::Normal,
                 });
             }
         }
@@ -213,8 +328,10 @@ impl VideoChip {
 
     /// Draws a tile anywhere on the screen using i16 coordinates for convenience. You can
     /// also provide various tile flags, like flipping, and specify a palette id.
-    pub fn draw_fg_tile(&mut self, data: DrawBundle) {
-        let size = TILE_SIZE as i16;
+    /// Returns the sprite index (used by the collision registers), if the sprite was inserted.
+    pub fn draw_fg_tile(&mut self, data: DrawBundle) -> Option<u8> {
+        let size_x = data.size.width();
+        let size_y = data.size.height();
 
         // Handle wrapping
         let wrapped_x: i16;
@@ -225,44 +342,43 @@ impl VideoChip {
 
             let w = self.w as i16;
             let h = self.h as i16;
-            let size = TILE_SIZE as i16;
 
-            let adjusted_x = screen_x + size;
-            let adjusted_y = screen_y + size;
+            let adjusted_x = screen_x + size_x;
+            let adjusted_y = screen_y + size_y;
 
             // Apply proper modulo wrapping
             let wrapped_adjusted_x =
-                ((adjusted_x % (w + size * 2)) + (w + size * 2)) % (w + size * 2);
+                ((adjusted_x % (w + size_x * 2)) + (w + size_x * 2)) % (w + size_x * 2);
             let wrapped_adjusted_y =
-                ((adjusted_y % (h + size * 2)) + (h + size * 2)) % (h + size * 2);
+                ((adjusted_y % (h + size_y * 2)) + (h + size_y * 2)) % (h + size_y * 2);
 
             // Adjust back to get the final coordinates
-            wrapped_x = wrapped_adjusted_x - size;
-            wrapped_y = wrapped_adjusted_y - size;
+            wrapped_x = wrapped_adjusted_x - size_x;
+            wrapped_y = wrapped_adjusted_y - size_y;
         } else {
             let max_x = self.scroll.x + self.max_x() as i16;
-            if data.x + size < self.scroll.x || data.x > max_x {
-                return;
+            if data.x + size_x < self.scroll.x || data.x > max_x {
+                return None;
             } else {
                 wrapped_x = data.x - self.scroll.x;
             }
             let max_y = self.scroll.y + self.max_y() as i16;
-            if data.y + size < self.scroll.y || data.y > max_y {
-                return;
+            if data.y + size_y < self.scroll.y || data.y > max_y {
+                return None;
             } else {
                 wrapped_y = data.y - self.scroll.y;
             }
         }
 
-        self.sprite_gen.insert(
-            wrapped_x,
-            wrapped_y,
-            self.w,
-            self.h,
-            data.flags,
-            data.id,
-            data.colors,
-        );
+        let sprite = SpriteEntry {
+            x: wrapped_x,
+            y: wrapped_y,
+            id: data.id,
+            flags: data.flags,
+            colors: data.colors,
+            size: data.size,
+        };
+        self.sprite_gen.insert(sprite, self.w, self.h)
     }
 
     pub fn frame_start(&mut self, is_paused: bool) {
@@ -275,6 +391,51 @@ impl VideoChip {
         self.reset_sprites();
     }
 
+    /// Submits the visible objects in the object table. They are inserted after
+    /// the sprites drawn during the frame, so they're drawn in front of them.
+    pub fn frame_finish(&mut self, is_paused: bool) {
+        if is_paused {
+            return;
+        }
+        self.submit_objects();
+    }
+
+    /// Inserts every visible object as sprites, in priority order.
+    pub fn submit_objects(&mut self) {
+        let (order, len) = self.objects.draw_order();
+        for &index in &order[..len] {
+            let Some(object) = self.objects.get(ObjectID(index)).copied() else {
+                continue;
+            };
+            match object.gfx {
+                ObjectGfx::Tile(id) => {
+                    self.draw_fg_tile(DrawBundle {
+                        x: object.x,
+                        y: object.y,
+                        id,
+                        flags: object.flags,
+                        colors: object.colors.unwrap_or_default(),
+                        size: object.size,
+                    });
+                },
+                ObjectGfx::Tilemap(map) => {
+                    self.draw_sprite(
+                        SpriteBundle {
+                            x: object.x,
+                            y: object.y,
+                            flip_x: object.flags.is_flipped_x(),
+                            flip_y: object.flags.is_flipped_y(),
+                            tile_offset: 0,
+                            palette_override: object.colors,
+                            behind_bg: object.flags.is_behind_bg(),
+                        },
+                        &map,
+                    );
+                },
+            }
+        }
+    }
+
     /// Returns an iterator over the visible screen pixels, yielding RGB colors for each pixel.
     /// Requires a reference to the Tile array and one for the BG Tilemap array.
     pub fn iter_pixels<'a, T>(
@@ -287,4 +448,29 @@ impl VideoChip {
     {
         PixelIter::new(self, video_banks, tilemaps)
     }
+
+    /// Renders the whole frame into a caller-provided RGBA32 buffer. Much faster than
+    /// collecting the results of [VideoChip::iter_pixels] one pixel at a time.
+    pub fn render_frame<'a, T>(
+        &'a self,
+        video_banks: &'a [&'a Bank],
+        tilemaps: &'a [&'a T],
+        frame: &mut [RGBA32],
+    ) where
+        &'a T: Into<TilemapRef<'a>>,
+    {
+        PixelIter::new(self, video_banks, tilemaps).render_frame(frame);
+    }
+
+    /// Renders the whole frame into a caller-provided byte buffer, 4 bytes (RGBA) per pixel.
+    pub fn render_frame_bytes<'a, T>(
+        &'a self,
+        video_banks: &'a [&'a Bank],
+        tilemaps: &'a [&'a T],
+        frame: &mut [u8],
+    ) where
+        &'a T: Into<TilemapRef<'a>>,
+    {
+        PixelIter::new(self, video_banks, tilemaps).render_frame_bytes(frame);
+    }
 }
@@ -0,0 +1,83 @@
+use crate::*;
+
+/// Determines which side of a window hides a layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WindowMask {
+    /// The window doesn't affect the layer.
+    #[default]
+    Off,
+    /// The layer is hidden inside the window.
+    Inside,
+    /// The layer is hidden outside the window (but still inside the viewport).
+    Outside,
+}
+
+/// Horizontal bounds of a window on a single scanline. Left is inclusive, right is exclusive,
+/// so an empty span (left >= right) means the window is closed on that line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct WindowSpan {
+    pub left: u16,
+    pub right: u16,
+}
+
+/// A "hardware" window that can hide the BG, sprites or color math on either side of it.
+/// Bounds are defined per scanline, and can be further modified by the line IRQ via
+/// "PixelIter::window_spans". When multiple windows affect the same layer, the layer is
+/// hidden if any of them hides it.
+/// Windows only affect what is displayed: sprite collisions are still detected.
+#[derive(Debug, Clone)]
+pub struct Window {
+    pub enabled: bool,
+    pub bg: WindowMask,
+    pub sprites: WindowMask,
+    pub color_math: WindowMask,
+    /// Bounds for every scanline. All lines start closed.
+    pub lines: [WindowSpan; MAX_RESOLUTION_Y],
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Window {
+    pub const fn new() -> Self {
+        Self {
+            enabled: false,
+            bg: WindowMask::Off,
+            sprites: WindowMask::Off,
+            color_math: WindowMask::Off,
+            lines: [WindowSpan { left: 0, right: 0 }; MAX_RESOLUTION_Y],
+        }
+    }
+
+    /// Closes the window on every line.
+    pub fn clear(&mut self) {
+        self.lines = [WindowSpan::default(); MAX_RESOLUTION_Y];
+    }
+
+    /// Sets the bounds of a single line.
+    pub fn set_span(&mut self, y: u16, left: u16, right: u16) {
+        if let Some(span) = self.lines.get_mut(y as usize) {
+            *span = WindowSpan { left, right };
+        }
+    }
+
+    /// Copies a table of spans, starting at line "top". Lines past the end are ignored.
+    pub fn set_lines(&mut self, top: u16, spans: &[WindowSpan]) {
+        let top = (top as usize).min(MAX_RESOLUTION_Y);
+        for (line, span) in self.lines[top..].iter_mut().zip(spans) {
+            *line = *span;
+        }
+    }
+
+    /// Closes the window and opens a single rectangle.
+    pub fn set_rect(&mut self, left: u16, top: u16, w: u16, h: u16) {
+        self.clear();
+        let right = left.saturating_add(w);
+        for y in top..top.saturating_add(h) {
+            self.set_span(y, left, right);
+        }
+    }
+}
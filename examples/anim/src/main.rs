@@ -106,6 +106,7 @@ fn main() -> TatoResult<()> {
                     flip_y: false,
                     tile_offset: 0,
                     palette_override: None,
+                    behind_bg: false,
                 },
             );
         }
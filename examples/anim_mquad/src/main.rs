@@ -111,7 +111,8 @@ async fn main() -> TatoResult<()> {
                     flip_x: entity.flip,
                     flip_y: false,
                     tile_offset: 0,
-                    palette_override: None
+                    palette_override: None,
+                    behind_bg: false
                 },
             );
         }
@@ -207,6 +207,7 @@ impl SceneA {
                 id: entity.tile,
                 flags: entity.flags,
                 colors: self.colors_shadow,
+                size: SpriteSize::Normal,
             });
         };
         for entity in &self.smileys {
@@ -223,6 +224,7 @@ impl SceneA {
                 id: entity.tile,
                 flags: entity.flags,
                 colors: entity.colors,
+                size: SpriteSize::Normal,
             });
         };
 
@@ -245,6 +247,7 @@ impl SceneA {
             id: self.smiley,
             flags: TileFlags::default(),
             colors: self.colors_cycle,
+            size: SpriteSize::Normal,
         });
 
         // ------------------- Return mode switch request -------------------
@@ -81,6 +81,7 @@ impl SceneB {
                 id: entity.tile,
                 flags: entity.flags,
                 colors: entity.colors,
+                size: SpriteSize::Normal,
             });
         }
 
@@ -90,6 +91,7 @@ impl SceneB {
             id: self.player.tile,
             flags: self.player.flags,
             colors: self.player.colors,
+            size: SpriteSize::Normal,
         });
 
         if t.pad.is_just_pressed(Button::Start) {
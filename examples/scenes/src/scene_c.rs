@@ -8,8 +8,6 @@ pub struct SceneC {
     counter: u64,
 }
 
-static mut LINE: u16 = 0;
-
 impl SceneC {
     pub fn new(t: &mut Tato, bank: &mut Bank, state: &mut State) -> TatoResult<Self> {
         t.video.reset_all();
@@ -36,10 +34,11 @@ impl SceneC {
             }
         }
 
-        // BG color raster effects
-        t.video.irq_line = Some(|iter, _chip, _tilemap| {
+        // BG color raster effects. The line offset is stored in the IRQ context,
+        // since IRQs can't capture state.
+        t.video.irq_line = Some(|iter, chip, _tilemap| {
             let y = iter.y();
-            let line = unsafe { y.wrapping_add(LINE) };
+            let line = y.wrapping_add(chip.irq_context[0] as u16);
             let color = &mut iter.bg_color;
 
             let scaled_line = line / 4;
@@ -54,10 +53,8 @@ impl SceneC {
 
     pub fn update(&mut self, t: &mut Tato, bg_bank: &mut Bank) -> Option<SceneChange> {
         if t.video.frame_number() % 4 == 0 {
-            unsafe {
-                LINE = LINE.wrapping_sub(1);
-                bg_bank.tiles.tiles[6].scroll(1, 0);
-            }
+            t.video.irq_context[0] = t.video.irq_context[0].wrapping_sub(1);
+            bg_bank.tiles.tiles[6].scroll(1, 0);
         }
 
         // Draw the sprite directly, no Entity
@@ -73,6 +70,7 @@ impl SceneC {
                 id: self.smiley,
                 flags: TileFlags::default(),
                 colors: [0, x as u8, 2, 3].into(),
+                size: SpriteSize::Normal,
             });
         }
 
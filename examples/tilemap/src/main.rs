@@ -20,25 +20,30 @@ fn main() -> TatoResult<()> {
     tato.video.bg_color = RGBA12::with_transparency(2, 3, 4, 7);
     tato.video.wrap_bg = true;
 
-    // Combine multiple banks into bank 0
+    // Combine multiple banks into bank 0. Colors shared by both banks are merged,
+    // so the source maps need their palettes remapped.
     bank.tiles.add(&Tile::default());
-    let patch_offset = bank.append(&BANK_PATCH).unwrap();
-    let smileys_offset = bank.append(&BANK_SMILEYS).unwrap();
+    let patch = bank.append(&BANK_PATCH).unwrap();
+    let smileys = bank.append(&BANK_SMILEYS).unwrap();
+    let mut map_patch = MAP_PATCH.clone();
+    map_patch.remap_colors(&patch.colors);
+    let mut map_smileys = MAP_SMILEYS.clone();
+    map_smileys.remap_colors(&smileys.colors);
 
     // Draw using the new direct tilemap API
     draw_patch_to_tilemap(
         &mut bg_map,
         Rect { x: 1, y: 1, w: 20, h: 4 },
-        &MAP_PATCH,
-        patch_offset,
+        &map_patch,
+        patch.tile_offset,
         true,
     );
     draw_tilemap_to_tilemap(
         &mut bg_map,
         Some(Rect { x: 3, y: 5, w: 16, h: 10 }),
-        &MAP_SMILEYS,
+        &map_smileys,
         None,
-        smileys_offset,
+        smileys.tile_offset,
     );
 
     // Backend
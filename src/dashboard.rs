@@ -81,6 +81,7 @@ pub struct Dashboard {
     debug_polys_world: Buffer<Polygon>,
     debug_polys_gui: Buffer<Polygon>,
     re_init_bank_texture: bool, // Will self-reset to false after generating texture
+    bank_versions: [Option<u32>; BANK_COUNT], // TileBank version used by each texture
     tile_pixels: [Buffer<u8, u32>; BANK_COUNT], // one per bank
     bank_texture_ids: [TextureId; BANK_COUNT], // GPU texture ID per bank, set by init_textures()
     tile_texture_dims: [(u16, u16); BANK_COUNT], // (width, height) of each bank's GPU texture
@@ -119,6 +120,7 @@ impl Dashboard {
             color_origin: RGBA12::with_transparency(7, 5, 3, 3),
             color_grid: RGBA12::with_transparency(4, 3, 2, 2),
             re_init_bank_texture: true,
+            bank_versions: [None; BANK_COUNT],
             // frame_arena,
             fixed_arena,
             bank_texture_ids,
@@ -149,7 +151,8 @@ impl Dashboard {
         })
     }
 
-    /// Call whenever bank changes have been performed.
+    /// Forces the bank textures to be regenerated. Only needed after writing to
+    /// "TileBank::tiles" directly, other bank changes are detected automatically.
     pub fn update_bank_texture(&mut self) {
         // Once the texture is updated, this self-resets to false.
         self.re_init_bank_texture = true;
@@ -398,13 +401,16 @@ impl Dashboard {
         // Update current tile texture, if changed
         if self.previous_tile_index != self.current_tile_index {
             self.previous_tile_index = self.current_tile_index;
-            let tile_index = self.current_tile_index.0 as usize;
             let bank = &banks[tato.video.bg_tile_bank as usize];
-            let tile = bank.tiles.tiles[tile_index];
+            // maps 2 or 4 bit colors to a max of 255;
+            let scale = match bank.tiles.mode() {
+                TileMode::Colors4 => 85,
+                TileMode::Colors16 => 17,
+            };
             let mut i = 0;
             for y in 0..TILE_SIZE {
                 for x in 0..TILE_SIZE {
-                    let color = tile.get_pixel(x, y) * 85; // maps 2 bit colors to a max of 255;
+                    let color = bank.tiles.get_pixel(self.current_tile_index, x, y) * scale;
                     // let color:RGBA32 = bank.colors.palette[color_index].into();
                     self.current_tile_pixels[i] = color;
                     self.current_tile_pixels[i + 1] = color;
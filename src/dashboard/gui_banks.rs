@@ -42,9 +42,15 @@ impl Dashboard {
     }
 
     fn update_tile_texture(&mut self, bank_index: usize, bank: &Bank, tiles_per_row: u16) {
+        // Tiles were added or removed (i.e. a bank checkpoint was popped) since the last update
+        let version = Some(bank.tiles.version());
+        let is_stale = self.bank_versions[bank_index] != version;
+        self.bank_versions[bank_index] = version;
+
         // Early return for empty banks
         if bank.tiles.count() == 0 {
-            // self.tile_pixels[bank_index].clear();
+            // Prevents the stale texture from being displayed
+            self.tile_texture_dims[bank_index] = (0, 0);
             return;
         }
 
@@ -61,7 +67,10 @@ impl Dashboard {
         // May need to reset entire fixed_arena if a single bank doesn't match.
         // Needs testing, I think I'm not running into a problem simply because the pixel count
         // always matches
-        if expected_size != self.tile_pixels[bank_index].len() || self.re_init_bank_texture {
+        if expected_size != self.tile_pixels[bank_index].len()
+            || self.re_init_bank_texture
+            || is_stale
+        {
             // Allocate buffer with correct size
             self.tile_pixels[bank_index].resize(&mut self.fixed_arena, expected_size as u32);
 
@@ -69,6 +78,11 @@ impl Dashboard {
             let pixels = self.tile_pixels[bank_index].as_slice_mut(&mut self.fixed_arena).unwrap();
             // Zero out pixels. If not done there may be garbage from previous tiles
             pixels.fill(0);
+            // maps 2 or 4 bit colors to a max of 255
+            let scale = match bank.tiles.mode() {
+                TileMode::Colors4 => 85,
+                TileMode::Colors16 => 17,
+            };
 
             for tile_index in 0..tile_count {
                 let tile_x = tile_index % tiles_per_row;
@@ -78,7 +92,7 @@ impl Dashboard {
                     for x in 0..TILE_SIZE as usize {
                         // get color
                         let color_index =
-                            bank.tiles.tiles[tile_index as usize].get_pixel(x as u8, y as u8);
+                            bank.tiles.get_pixel(TileID(tile_index as u8), x as u8, y as u8);
                         // get coordinates
                         let pixel_x = tile_x as usize * TILE_SIZE as usize + x;
                         let pixel_y = tile_y as usize * TILE_SIZE as usize + y;
@@ -86,9 +100,9 @@ impl Dashboard {
                         // Seems safe for now, may need to insert a check for i < pixels.len()
                         // if I get out-of-bounds errors.
                         // let color: RGBA32 = bank.colors.palette[color_index as usize].into();
-                        pixels[i] = color_index * 85;
-                        pixels[i + 1] = color_index * 85;
-                        pixels[i + 2] = color_index * 85;
+                        pixels[i] = color_index * scale;
+                        pixels[i + 1] = color_index * scale;
+                        pixels[i + 2] = color_index * scale;
                         pixels[i + 3] = 255;
                     }
                 }
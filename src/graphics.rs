@@ -351,3 +351,14 @@ pub fn tilemap_replace_color<const LEN: usize>(
         }
     }
 }
+
+/// Saves the bank's current tiles and colors, i.e. when entering a scene.
+/// See [Bank::push_checkpoint].
+pub fn bank_push(bank: &mut Bank) -> TatoResult<()> {
+    bank.push_checkpoint().map_err(|_| TatoError::CheckpointStackOverflow)
+}
+
+/// Discards everything added to the bank since the last "bank_push", i.e. when leaving a scene.
+pub fn bank_pop(bank: &mut Bank) -> TatoResult<BankCheckpoint> {
+    bank.pop_checkpoint().map_err(|_| TatoError::NoTilesetToPop)
+}
@@ -107,6 +107,7 @@ impl Tato {
             ))
         }
 
+        self.video.frame_finish(self.paused);
         self.frame_finished = true;
         self.frame_started = false;
     }
@@ -129,4 +130,16 @@ impl Tato {
     {
         self.video.iter_pixels(banks, tilemaps)
     }
+
+    /// Renders the entire frame into a byte buffer (RGBA, 4 bytes per pixel).
+    pub fn render_frame_bytes<'a, T>(
+        &'a self,
+        banks: &'a [&'a Bank],
+        tilemaps: &'a [&'a T],
+        frame: &mut [u8],
+    ) where
+        &'a T: Into<TilemapRef<'a>>,
+    {
+        self.video.render_frame_bytes(banks, tilemaps, frame)
+    }
 }